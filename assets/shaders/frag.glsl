@@ -5,12 +5,25 @@ uniform vec3 lightPosition;
 
 uniform float time;
 
+uniform sampler2DArray blockTextures;
+
 in vec3 normal;
 in vec3 fragPos;
+in float emission;
+in float occlusion;
+in vec2 uv;
+flat in float textureLayer;
 
 out vec4 fragColor;
 
 void main() {
+    vec4 texel = texture(blockTextures, vec3(uv, textureLayer));
+
+    // Leave out the see-through pixels of textures
+    if (texel.a < 0.5) {
+        discard;
+    }
+
     vec3 lightColor = vec3(0.5, 0.8, 1.0);
 
     // Apply ambient lighting
@@ -34,5 +47,11 @@ void main() {
     // Apply all lighting techniques
     vec3 result = ambient + diffuse; // + specular;
 
+    // Darken corners and crevices that are enclosed by blocks
+    result *= occlusion;
+
+    // Colour the block, and let emissive blocks light themselves up
+    result = texel.rgb * max(result, vec3(emission));
+
     fragColor = vec4(result, 1.0);
 }
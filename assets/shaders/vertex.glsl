@@ -4,18 +4,68 @@ uniform mat4 view;
 uniform mat4 projection;
 uniform mat4 model;
 
-layout (location = 0) in vec3 i_pos;
-layout (location = 1) in vec3 i_normal;
+// the world position of the chunk the mesh belongs to
+uniform vec3 chunkOffset;
+
+// a vertex packed into two integers, laid out as in `PackedVertex`
+layout (location = 0) in uvec2 i_packed;
+
+// how many steps each block is split into by packed positions
+const float POSITION_SCALE = 64.0;
+const float MAX_LIGHT_LEVEL = 15.0;
+
+// indexed by `FaceDirection::index`
+const vec3 NORMALS[6] = vec3[](
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(1.0, 0.0, 0.0),
+    vec3(0.0, 0.0, -1.0),
+    vec3(0.0, 0.0, 1.0)
+);
+
+// the axes that u and v run along on each face, defined from
+// `block_textures::FACE_UV_AXES` when the shader is loaded
+const vec3 UV_AXES[12] = FACE_UV_AXES;
+
+// how much ambient light reaches a vertex at each ambient occlusion level
+const float OCCLUSION_BRIGHTNESS[4] = float[](0.4, 0.6, 0.8, 1.0);
 
 out vec3 normal;
 out vec3 fragPos;
+out float emission;
+out float occlusion;
+out vec2 uv;
+flat out float textureLayer;
 
 void main()
 {
-    gl_Position = projection * view * model * vec4(i_pos, 1.0);
+    // unpack the vertex
+    uint x = i_packed.x & 0x7FFu;
+    uint z = (i_packed.x >> 11) & 0x7FFu;
+    uint face = (i_packed.x >> 22) & 0x7u;
+    uint ao = (i_packed.x >> 25) & 0x3u;
+    uint light = (i_packed.x >> 27) & 0xFu;
+    uint y = i_packed.y & 0x3FFFu;
+    uint layer = (i_packed.y >> 14) & 0xFFFu;
+
+    vec3 localPos = vec3(x, y, z) / POSITION_SCALE;
+    vec3 pos = chunkOffset + localPos;
+
+    gl_Position = projection * view * model * vec4(pos, 1.0);
 
     // give the normal to the fragment shader
-    normal = i_normal;
-    // because there is no model matrix, the fragPos is the same as i_pos
-    fragPos = i_pos;
-}
\ No newline at end of file
+    normal = NORMALS[face];
+    // because there is no model matrix, the fragPos is the same as pos
+    fragPos = pos;
+
+    // the light emitted by the block comes from the block registry
+    emission = float(light) / MAX_LIGHT_LEVEL;
+
+    // how much ambient light reaches the vertex, darkened by nearby blocks
+    occlusion = OCCLUSION_BRIGHTNESS[ao];
+
+    // which layer of the block textures to show, and where on it
+    uv = vec2(dot(localPos, UV_AXES[face * 2u]), dot(localPos, UV_AXES[face * 2u + 1u]));
+    textureLayer = float(layer);
+}
@@ -1,84 +1,399 @@
-use std::ops::{Index, IndexMut};
+#![allow(dead_code)]
+
+use std::mem::size_of;
 
 use crate::{
-    chunk::{CHUNK_HEIGHT, CHUNK_WIDTH},
-    utils::get_chunk_index,
+    chunk::{CHUNK_WIDTH, SECTION_HEIGHT, SECTION_VOLUME},
+    utils::{get_section_coordinate, get_section_index},
     voxel::{Voxel, VoxelKind},
 };
 
-type BlockIndex = (usize, usize, usize);
+/// The position of a block relative to the chunk (or section) it is in.
+pub type BlockIndex = (usize, usize, usize);
+
+/// The bit widths a palette index can be stored with, from smallest to largest.
+const PALETTE_BIT_WIDTHS: [u8; 5] = [1, 2, 4, 8, 16];
 
-/// Represents all of the voxels in a chunk.
-#[derive(Debug)]
+/// Represents all of the voxels in a chunk section.
+///
+/// The voxels are palette compressed: every distinct voxel is stored once in
+/// the palette, and each block only stores an index into it. The indices are
+/// packed into `u64` words using the smallest bit width that can address the
+/// whole palette, and the width grows as new voxels are written.
+#[derive(Debug, Clone)]
 pub struct BlockCollection {
-    /// The actual voxel data.
-    voxels: Vec<Voxel>,
+    /// The distinct voxels that appear in the collection.
+    palette: Vec<Voxel>,
+
+    /// The number of bits used to store a single palette index.
+    bits_per_block: u8,
+
+    /// The packed palette indices, indexed with `get_section_index`.
+    data: Vec<u64>,
 }
 
 impl BlockCollection {
-    /// Creates a new block collection.
+    /// Creates a new block collection, filled with air.
     pub fn new() -> Self {
+        Self::new_filled(VoxelKind::AIR)
+    }
+
+    /// Creates a new block collection, and fills it with the given voxel.
+    pub fn new_filled(voxel: impl Into<Voxel>) -> Self {
+        let bits_per_block = PALETTE_BIT_WIDTHS[0];
+
         Self {
-            voxels: {
-                // Pre-allocate the voxel vector
-                let mut voxels = Vec::new();
-                voxels.reserve(CHUNK_WIDTH * CHUNK_WIDTH * CHUNK_WIDTH);
-                voxels
-            },
+            palette: vec![voxel.into()],
+            bits_per_block,
+            data: vec![0; Self::word_count(bits_per_block)],
         }
     }
 
-    /// Creates a new block collection, and fills it with the given voxel kind.
-    pub fn new_filled(chunk_offset: (i32, i32), voxel: VoxelKind) -> Self {
-        Self {
-            voxels: {
-                // Pre-allocate the voxel vector
-                let mut voxels = Vec::new();
-                voxels.reserve(CHUNK_WIDTH * CHUNK_WIDTH * CHUNK_WIDTH);
-
-                // Fill the voxel vector with the given voxel kind
-                for x in 0..CHUNK_WIDTH {
-                    for z in 0..CHUNK_WIDTH {
-                        for y in 0..CHUNK_HEIGHT {
-                            let x = x as i32 + chunk_offset.0 * CHUNK_WIDTH as i32;
-                            let z = z as i32 + chunk_offset.1 * CHUNK_WIDTH as i32;
-                            let y = y as i32;
-
-                            voxels.push(Voxel {
-                                position: (x, y, z),
-                                kind: voxel,
-                            });
-                        }
-                    }
-                }
+    /// Returns true if the position lies inside of the section.
+    pub fn in_bounds(pos: BlockIndex) -> bool {
+        let (x, y, z) = pos;
 
-                voxels
-            },
-        }
+        x < CHUNK_WIDTH && y < SECTION_HEIGHT && z < CHUNK_WIDTH
     }
 
     /// Gets the voxel at the given position.
-    pub fn get(&self, pos: BlockIndex) -> Option<&Voxel> {
-        self.voxels.get(get_chunk_index(pos))
+    pub fn get(&self, pos: BlockIndex) -> Option<Voxel> {
+        if !Self::in_bounds(pos) {
+            return None;
+        }
+
+        Some(self.get_by_index(get_section_index(pos)))
     }
 
     /// Gets the voxel given the chunk-block index.
-    /// Panics if the index is out of bounds (i.e. greater than `CHUNK_WIDTH * CHUNK_WIDTH * CHUNK_WIDTH`).
-    pub fn get_by_index(&self, index: usize) -> &Voxel {
-        &self.voxels[index]
+    /// Panics if the index is out of bounds (i.e. greater than `SECTION_VOLUME`).
+    pub fn get_by_index(&self, index: usize) -> Voxel {
+        self.palette[self.read_index(index)]
     }
-}
 
-impl Index<BlockIndex> for BlockCollection {
-    type Output = Voxel;
+    /// Sets the voxel at the given position.
+    /// Panics if the position is outside of the section.
+    pub fn set(&mut self, pos: BlockIndex, voxel: impl Into<Voxel>) {
+        assert!(
+            Self::in_bounds(pos),
+            "Block position {:?} is outside of the section",
+            pos
+        );
+
+        let palette_index = self.palette_index(voxel.into());
+        self.write_index(get_section_index(pos), palette_index);
+    }
+
+    /// Sets every voxel in the collection to the given voxel.
+    pub fn fill(&mut self, voxel: impl Into<Voxel>) {
+        *self = Self::new_filled(voxel);
+    }
+
+    /// Iterates over every voxel, along with its position in the section.
+    pub fn iter(&self) -> impl Iterator<Item = (BlockIndex, Voxel)> + '_ {
+        (0..SECTION_VOLUME).map(|index| (get_section_coordinate(index), self.get_by_index(index)))
+    }
+
+    /// Returns the number of distinct voxels in the palette.
+    pub fn palette_len(&self) -> usize {
+        self.palette.len()
+    }
+
+    /// Returns the number of bits used to store each block.
+    pub fn bits_per_block(&self) -> u8 {
+        self.bits_per_block
+    }
+
+    /// Returns the palette of distinct voxels.
+    pub fn palette(&self) -> &[Voxel] {
+        &self.palette
+    }
+
+    /// Returns the packed palette indices.
+    pub fn data(&self) -> &[u64] {
+        &self.data
+    }
+
+    /// Creates a block collection from a palette and its packed indices, as
+    /// returned by `palette` and `data`. Fails if the parts don't fit together.
+    pub fn from_raw(
+        palette: Vec<Voxel>,
+        bits_per_block: u8,
+        data: Vec<u64>,
+    ) -> Result<Self, String> {
+        if !PALETTE_BIT_WIDTHS.contains(&bits_per_block) {
+            return Err(format!("Invalid bits per block: {}", bits_per_block));
+        }
+        if palette.is_empty() || palette.len() > 1 << bits_per_block {
+            return Err(format!(
+                "Palette of {} entries can't be stored with {} bits per block",
+                palette.len(),
+                bits_per_block
+            ));
+        }
+        if data.len() != Self::word_count(bits_per_block) {
+            return Err(format!(
+                "Expected {} words of block data, found {}",
+                Self::word_count(bits_per_block),
+                data.len()
+            ));
+        }
+
+        let collection = Self {
+            palette,
+            bits_per_block,
+            data,
+        };
+
+        if (0..SECTION_VOLUME).any(|index| collection.read_index(index) >= collection.palette.len())
+        {
+            return Err("Block data refers to a missing palette entry".to_string());
+        }
+
+        Ok(collection)
+    }
 
-    fn index(&self, index: BlockIndex) -> &Self::Output {
-        self.get(index).unwrap()
+    /// Returns the approximate number of bytes used by the collection.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.palette.capacity() * size_of::<Voxel>()
+            + self.data.capacity() * size_of::<u64>()
+    }
+
+    /// Removes all voxels from the palette that are no longer used, and shrinks
+    /// the bit width to the smallest one that can still address the palette.
+    pub fn compact(&mut self) {
+        let mut used = vec![false; self.palette.len()];
+
+        for index in 0..SECTION_VOLUME {
+            used[self.read_index(index)] = true;
+        }
+
+        if used.iter().all(|used| *used) {
+            return;
+        }
+
+        // Map the old palette indices onto the new, smaller palette
+        let mut remap = vec![0; self.palette.len()];
+        let mut palette = Vec::new();
+
+        for (old_index, voxel) in self.palette.iter().enumerate() {
+            if used[old_index] {
+                remap[old_index] = palette.len();
+                palette.push(*voxel);
+            }
+        }
+
+        let mut compacted = Self {
+            bits_per_block: Self::bits_for_palette(palette.len()),
+            palette,
+            data: Vec::new(),
+        };
+        compacted.data = vec![0; Self::word_count(compacted.bits_per_block)];
+
+        for index in 0..SECTION_VOLUME {
+            compacted.write_index(index, remap[self.read_index(index)]);
+        }
+
+        compacted.palette.shrink_to_fit();
+        *self = compacted;
+    }
+
+    /// Returns the palette index of the voxel, adding it to the palette (and
+    /// widening the stored indices) if it is not in the palette yet.
+    fn palette_index(&mut self, voxel: Voxel) -> usize {
+        if let Some(index) = self.palette.iter().position(|v| *v == voxel) {
+            return index;
+        }
+
+        self.palette.push(voxel);
+
+        let bits = Self::bits_for_palette(self.palette.len());
+        if bits != self.bits_per_block {
+            self.resize(bits);
+        }
+
+        self.palette.len() - 1
+    }
+
+    /// Repacks all of the stored indices with a new bit width.
+    fn resize(&mut self, bits_per_block: u8) {
+        let mut resized = Self {
+            palette: Vec::new(),
+            bits_per_block,
+            data: vec![0; Self::word_count(bits_per_block)],
+        };
+
+        for index in 0..SECTION_VOLUME {
+            resized.write_index(index, self.read_index(index));
+        }
+
+        self.bits_per_block = resized.bits_per_block;
+        self.data = resized.data;
+    }
+
+    /// Reads the palette index stored for the block at the given chunk-block index.
+    fn read_index(&self, index: usize) -> usize {
+        let bits = self.bits_per_block as usize;
+        let per_word = 64 / bits;
+        let mask = (1u64 << bits) - 1;
+
+        let shift = (index % per_word) * bits;
+        ((self.data[index / per_word] >> shift) & mask) as usize
+    }
+
+    /// Writes the palette index for the block at the given chunk-block index.
+    fn write_index(&mut self, index: usize, palette_index: usize) {
+        let bits = self.bits_per_block as usize;
+        let per_word = 64 / bits;
+        let mask = (1u64 << bits) - 1;
+
+        let shift = (index % per_word) * bits;
+        let word = &mut self.data[index / per_word];
+
+        *word = (*word & !(mask << shift)) | ((palette_index as u64 & mask) << shift);
+    }
+
+    /// Returns the smallest supported bit width that can address a palette of the given size.
+    fn bits_for_palette(len: usize) -> u8 {
+        *PALETTE_BIT_WIDTHS
+            .iter()
+            .find(|bits| len <= 1 << **bits)
+            .expect("Palette has more entries than can be addressed")
+    }
+
+    /// Returns the number of `u64` words needed to store every block at the given bit width.
+    fn word_count(bits_per_block: u8) -> usize {
+        SECTION_VOLUME.div_ceil(64 / bits_per_block as usize)
+    }
+}
+
+impl Default for BlockCollection {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl IndexMut<BlockIndex> for BlockCollection {
-    fn index_mut(&mut self, index: BlockIndex) -> &mut Self::Output {
-        self.voxels.index_mut(get_chunk_index(index))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The position of the nth block written by a test.
+    fn position(n: usize) -> BlockIndex {
+        get_section_coordinate(n * 13 % SECTION_VOLUME)
+    }
+
+    #[test]
+    fn palettes_grow_through_every_bit_width() {
+        let mut blocks = BlockCollection::new();
+        assert_eq!((blocks.palette_len(), blocks.bits_per_block()), (1, 1));
+
+        // The palette size at which each wider bit width is needed
+        let mut widths = vec![(2, 1), (3, 2), (5, 4), (17, 8), (257, 16)].into_iter();
+        let mut next = widths.next();
+
+        for n in 1..300 {
+            blocks.set(position(n), VoxelKind(n as u16));
+
+            if let Some((len, bits)) = next {
+                if blocks.palette_len() == len {
+                    assert_eq!(blocks.bits_per_block(), bits, "{} entries", len);
+                    next = widths.next();
+                }
+            }
+
+            assert_eq!(
+                blocks.data().len(),
+                SECTION_VOLUME.div_ceil(64 / blocks.bits_per_block() as usize)
+            );
+
+            // Blocks written at narrower widths are kept when the width grows
+            for m in 1..=n {
+                assert_eq!(blocks.get(position(m)), Some(VoxelKind(m as u16).into()));
+            }
+        }
+
+        assert_eq!(next, None);
+        assert_eq!(blocks.get(position(0)), Some(VoxelKind::AIR.into()));
+    }
+
+    #[test]
+    fn writing_a_known_voxel_keeps_the_palette() {
+        let mut blocks = BlockCollection::new_filled(VoxelKind(3));
+        blocks.set((1, 2, 3), VoxelKind(4));
+        blocks.set((4, 5, 6), VoxelKind(4));
+        blocks.set((7, 8, 9), VoxelKind(3));
+
+        assert_eq!(blocks.palette(), [VoxelKind(3).into(), VoxelKind(4).into()]);
+        assert_eq!(blocks.bits_per_block(), 1);
+        assert_eq!(blocks.get((4, 5, 6)), Some(VoxelKind(4).into()));
+        assert_eq!(blocks.get((16, 0, 0)), None);
+    }
+
+    #[test]
+    fn compacting_drops_unused_voxels_and_narrows_the_bit_width() {
+        let mut blocks = BlockCollection::new();
+        for n in 1..20 {
+            blocks.set(position(n), VoxelKind(n as u16));
+        }
+        assert_eq!((blocks.palette_len(), blocks.bits_per_block()), (20, 8));
+
+        // Only air and two of the kinds are left
+        for n in 1..20 {
+            if n != 5 && n != 11 {
+                blocks.set(position(n), VoxelKind::AIR);
+            }
+        }
+
+        blocks.compact();
+
+        assert_eq!(
+            blocks.palette(),
+            [VoxelKind::AIR, VoxelKind(5), VoxelKind(11)].map(Voxel::from)
+        );
+        assert_eq!(blocks.bits_per_block(), 2);
+        assert_eq!(blocks.data().len(), SECTION_VOLUME / 32);
+
+        for (pos, voxel) in blocks.iter() {
+            let expected = match pos {
+                pos if pos == position(5) => VoxelKind(5),
+                pos if pos == position(11) => VoxelKind(11),
+                _ => VoxelKind::AIR,
+            };
+
+            assert_eq!(voxel, expected.into(), "{:?}", pos);
+        }
+
+        // New voxels are added to the compacted palette
+        blocks.set((0, 0, 0), VoxelKind(7));
+        assert_eq!((blocks.palette_len(), blocks.bits_per_block()), (4, 2));
+        assert_eq!(blocks.get((0, 0, 0)), Some(VoxelKind(7).into()));
+    }
+
+    #[test]
+    fn collections_are_rebuilt_from_their_raw_parts() {
+        let mut blocks = BlockCollection::new();
+        for n in 1..6 {
+            blocks.set(position(n), VoxelKind(n as u16));
+        }
+
+        let rebuilt = BlockCollection::from_raw(
+            blocks.palette().to_vec(),
+            blocks.bits_per_block(),
+            blocks.data().to_vec(),
+        )
+        .unwrap();
+
+        assert!(rebuilt.iter().eq(blocks.iter()));
+
+        let palette = blocks.palette().to_vec();
+        let data = blocks.data().to_vec();
+
+        // A width that isn't supported, a palette too large for the width,
+        // missing words and indices past the end of the palette
+        assert!(BlockCollection::from_raw(palette.clone(), 3, data.clone()).is_err());
+        assert!(BlockCollection::from_raw(palette.clone(), 2, data.clone()).is_err());
+        assert!(BlockCollection::from_raw(palette.clone(), 4, data[1..].to_vec()).is_err());
+        assert!(BlockCollection::from_raw(palette[..3].to_vec(), 4, data).is_err());
     }
 }
@@ -0,0 +1,328 @@
+#![allow(dead_code)]
+
+use crate::rendering::mesh::FaceDirection;
+
+/// The highest growth stage of a crop.
+pub const MAX_GROWTH_STAGE: u8 = 7;
+/// The highest level of a water block.
+pub const MAX_WATER_LEVEL: u8 = 15;
+
+// The layout of the properties in the packed state.
+const FACING_SHIFT: u16 = 0;
+const FACING_MASK: u16 = 0b111;
+const HALF_SHIFT: u16 = 3;
+const HALF_MASK: u16 = 0b1;
+const GROWTH_STAGE_SHIFT: u16 = 4;
+const GROWTH_STAGE_MASK: u16 = 0b111;
+const WATER_LEVEL_SHIFT: u16 = 7;
+const WATER_LEVEL_MASK: u16 = 0b1111;
+
+/// The properties a block type can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockProperty {
+    /// The direction the block is facing (logs, stairs).
+    Facing,
+    /// Which half of the block is filled (slabs).
+    Half,
+    /// How far the block has grown (crops).
+    GrowthStage,
+    /// How full the block is (water).
+    WaterLevel,
+}
+
+/// The direction a block is facing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facing {
+    Up,
+    Down,
+    /// Towards negative z.
+    North,
+    /// Towards positive z.
+    South,
+    /// Towards positive x.
+    East,
+    /// Towards negative x.
+    West,
+}
+
+/// Which half of a block is filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Half {
+    Lower,
+    Upper,
+}
+
+/// The typed properties of a single block, packed into 16 bits so that it
+/// can be stored alongside the block type in a chunk's palette.
+/// Properties a block type doesn't have are left at their default value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BlockState(pub u16);
+
+impl BlockProperty {
+    /// Returns a list of all of the properties.
+    pub fn all() -> [BlockProperty; 4] {
+        [
+            BlockProperty::Facing,
+            BlockProperty::Half,
+            BlockProperty::GrowthStage,
+            BlockProperty::WaterLevel,
+        ]
+    }
+
+    /// Returns the name of the property, as used in the block registry.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            BlockProperty::Facing => "facing",
+            BlockProperty::Half => "half",
+            BlockProperty::GrowthStage => "growth_stage",
+            BlockProperty::WaterLevel => "water_level",
+        }
+    }
+
+    /// Gets a property from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|property| property.name() == name)
+    }
+
+    /// Returns the bits of the packed state that store the property.
+    const fn mask(&self) -> u16 {
+        match self {
+            BlockProperty::Facing => FACING_MASK << FACING_SHIFT,
+            BlockProperty::Half => HALF_MASK << HALF_SHIFT,
+            BlockProperty::GrowthStage => GROWTH_STAGE_MASK << GROWTH_STAGE_SHIFT,
+            BlockProperty::WaterLevel => WATER_LEVEL_MASK << WATER_LEVEL_SHIFT,
+        }
+    }
+}
+
+impl Facing {
+    /// Returns a list of all of the directions.
+    pub fn all() -> [Facing; 6] {
+        [
+            Facing::Up,
+            Facing::Down,
+            Facing::North,
+            Facing::South,
+            Facing::East,
+            Facing::West,
+        ]
+    }
+
+    /// Returns the lowercase name of the direction.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Facing::Up => "up",
+            Facing::Down => "down",
+            Facing::North => "north",
+            Facing::South => "south",
+            Facing::East => "east",
+            Facing::West => "west",
+        }
+    }
+
+    /// Gets a direction from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|facing| facing.name() == name)
+    }
+
+    /// Returns the face of a cube that points in this direction.
+    pub const fn face(&self) -> FaceDirection {
+        match self {
+            Facing::Up => FaceDirection::Up,
+            Facing::Down => FaceDirection::Down,
+            Facing::North => FaceDirection::Front,
+            Facing::South => FaceDirection::Back,
+            Facing::East => FaceDirection::Right,
+            Facing::West => FaceDirection::Left,
+        }
+    }
+
+    /// Rotates a face of a block's model, so that the model's up face points
+    /// in this direction. Returns the face of the world it ends up on.
+    pub const fn rotate(&self, model_face: FaceDirection) -> FaceDirection {
+        use FaceDirection::*;
+
+        match (self, model_face) {
+            (Facing::Up, face) => face,
+            // Flip around the x axis
+            (Facing::Down, Up) => Down,
+            (Facing::Down, Down) => Up,
+            (Facing::Down, Front) => Back,
+            (Facing::Down, Back) => Front,
+            // Tip over towards negative z
+            (Facing::North, Up) => Front,
+            (Facing::North, Front) => Down,
+            (Facing::North, Down) => Back,
+            (Facing::North, Back) => Up,
+            // Tip over towards positive z
+            (Facing::South, Up) => Back,
+            (Facing::South, Back) => Down,
+            (Facing::South, Down) => Front,
+            (Facing::South, Front) => Up,
+            // Tip over towards positive x
+            (Facing::East, Up) => Right,
+            (Facing::East, Right) => Down,
+            (Facing::East, Down) => Left,
+            (Facing::East, Left) => Up,
+            // Tip over towards negative x
+            (Facing::West, Up) => Left,
+            (Facing::West, Left) => Down,
+            (Facing::West, Down) => Right,
+            (Facing::West, Right) => Up,
+            // Faces on the rotation axis stay where they are
+            (_, face) => face,
+        }
+    }
+
+    /// Returns the face of the block's model that ends up on the given face of
+    /// the world. This is the inverse of `Facing::rotate`.
+    pub fn model_face(&self, world_face: FaceDirection) -> FaceDirection {
+        FaceDirection::all()
+            .into_iter()
+            .find(|face| self.rotate(*face) == world_face)
+            .unwrap()
+    }
+}
+
+impl Half {
+    /// Returns the lowercase name of the half.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Half::Lower => "lower",
+            Half::Upper => "upper",
+        }
+    }
+
+    /// Gets a half from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Half::Lower, Half::Upper]
+            .into_iter()
+            .find(|half| half.name() == name)
+    }
+}
+
+impl BlockState {
+    /// Returns the direction the block is facing.
+    pub const fn facing(&self) -> Facing {
+        match (self.0 >> FACING_SHIFT) & FACING_MASK {
+            1 => Facing::Down,
+            2 => Facing::North,
+            3 => Facing::South,
+            4 => Facing::East,
+            5 => Facing::West,
+            _ => Facing::Up,
+        }
+    }
+
+    /// Returns a copy of the state facing in a different direction.
+    pub const fn with_facing(self, facing: Facing) -> Self {
+        let value = match facing {
+            Facing::Up => 0,
+            Facing::Down => 1,
+            Facing::North => 2,
+            Facing::South => 3,
+            Facing::East => 4,
+            Facing::West => 5,
+        };
+
+        self.with_bits(FACING_SHIFT, FACING_MASK, value)
+    }
+
+    /// Returns which half of the block is filled.
+    pub const fn half(&self) -> Half {
+        match (self.0 >> HALF_SHIFT) & HALF_MASK {
+            0 => Half::Lower,
+            _ => Half::Upper,
+        }
+    }
+
+    /// Returns a copy of the state with a different half filled.
+    pub const fn with_half(self, half: Half) -> Self {
+        let value = match half {
+            Half::Lower => 0,
+            Half::Upper => 1,
+        };
+
+        self.with_bits(HALF_SHIFT, HALF_MASK, value)
+    }
+
+    /// Returns the growth stage of the block, from 0 to `MAX_GROWTH_STAGE`.
+    pub const fn growth_stage(&self) -> u8 {
+        ((self.0 >> GROWTH_STAGE_SHIFT) & GROWTH_STAGE_MASK) as u8
+    }
+
+    /// Returns a copy of the state with a different growth stage.
+    /// The stage is clamped to `MAX_GROWTH_STAGE`.
+    pub const fn with_growth_stage(self, stage: u8) -> Self {
+        let stage = if stage > MAX_GROWTH_STAGE {
+            MAX_GROWTH_STAGE
+        } else {
+            stage
+        };
+
+        self.with_bits(GROWTH_STAGE_SHIFT, GROWTH_STAGE_MASK, stage as u16)
+    }
+
+    /// Returns the water level of the block, from 0 to `MAX_WATER_LEVEL`.
+    pub const fn water_level(&self) -> u8 {
+        ((self.0 >> WATER_LEVEL_SHIFT) & WATER_LEVEL_MASK) as u8
+    }
+
+    /// Returns a copy of the state with a different water level.
+    /// The level is clamped to `MAX_WATER_LEVEL`.
+    pub const fn with_water_level(self, level: u8) -> Self {
+        let level = if level > MAX_WATER_LEVEL {
+            MAX_WATER_LEVEL
+        } else {
+            level
+        };
+
+        self.with_bits(WATER_LEVEL_SHIFT, WATER_LEVEL_MASK, level as u16)
+    }
+
+    /// Resets every property not in the list to its default value, so that
+    /// equal blocks always share the same state.
+    pub fn retain(self, properties: &[BlockProperty]) -> Self {
+        let mask = properties
+            .iter()
+            .fold(0, |mask, property| mask | property.mask());
+
+        Self(self.0 & mask)
+    }
+
+    /// Returns the value of a property as text, such as `north` or `3`.
+    pub fn value_name(&self, property: BlockProperty) -> String {
+        match property {
+            BlockProperty::Facing => self.facing().name().to_string(),
+            BlockProperty::Half => self.half().name().to_string(),
+            BlockProperty::GrowthStage => self.growth_stage().to_string(),
+            BlockProperty::WaterLevel => self.water_level().to_string(),
+        }
+    }
+
+    /// Returns a copy of the state with a property parsed from text.
+    /// Returns `None` if the value isn't valid for the property.
+    pub fn with_value_name(self, property: BlockProperty, value: &str) -> Option<Self> {
+        let state = match property {
+            BlockProperty::Facing => self.with_facing(Facing::from_name(value)?),
+            BlockProperty::Half => self.with_half(Half::from_name(value)?),
+            BlockProperty::GrowthStage => {
+                let stage = value.parse().ok().filter(|s| *s <= MAX_GROWTH_STAGE)?;
+                self.with_growth_stage(stage)
+            }
+            BlockProperty::WaterLevel => {
+                let level = value.parse().ok().filter(|l| *l <= MAX_WATER_LEVEL)?;
+                self.with_water_level(level)
+            }
+        };
+
+        Some(state)
+    }
+
+    /// Replaces the bits of a single property.
+    const fn with_bits(self, shift: u16, mask: u16, value: u16) -> Self {
+        Self((self.0 & !(mask << shift)) | ((value & mask) << shift))
+    }
+}
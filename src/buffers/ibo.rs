@@ -37,6 +37,13 @@ impl Ibo {
         }
     }
 
+    /// Deletes the IBO. It must not be used afterwards.
+    pub fn delete(&self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+
     /// Sets the data of the IBO.
     pub fn set_data(&mut self, data: &[u32], usage: GLenum) {
         unsafe {
@@ -28,6 +28,13 @@ impl Vao {
         }
     }
 
+    /// Deletes the VAO. It must not be used afterwards.
+    pub fn delete(&self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.id);
+        }
+    }
+
     pub fn set_attribute(
         &mut self,
         index: u32,
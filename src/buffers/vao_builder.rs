@@ -1,13 +1,39 @@
 use std::mem::size_of;
 
-use gl::types::GLvoid;
+use gl::types::{GLenum, GLvoid};
 use owo_colors::OwoColorize;
 
 use super::vao::Vao;
 
-/// Stores the number of elements in the layer, and the overall size.
+/// A type that the elements of a layer can have.
+pub trait AttributeType {
+    /// The OpenGL type of the elements.
+    const GL_TYPE: GLenum;
+
+    /// Whether the shader reads the elements as integers, rather than
+    /// converting them to floats.
+    const INTEGER: bool;
+}
+
+impl AttributeType for f32 {
+    const GL_TYPE: GLenum = gl::FLOAT;
+    const INTEGER: bool = false;
+}
+
+impl AttributeType for i32 {
+    const GL_TYPE: GLenum = gl::INT;
+    const INTEGER: bool = true;
+}
+
+impl AttributeType for u32 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_INT;
+    const INTEGER: bool = true;
+}
+
+/// Stores the number of elements in the layer, the size of each element, and
+/// their type.
 #[derive(Debug)]
-struct LayerSize(usize, usize);
+struct LayerSize(usize, usize, GLenum, bool);
 
 /// A struct that is used to build a VAO.
 #[derive(Debug)]
@@ -38,8 +64,9 @@ impl VaoBuilder {
     }
 
     /// Adds a layer to the VAO.
-    pub fn add_layer<T>(&mut self, n: usize) -> &mut Self {
-        self.layers.push(LayerSize(n, size_of::<T>()));
+    pub fn add_layer<T: AttributeType>(&mut self, n: usize) -> &mut Self {
+        self.layers
+            .push(LayerSize(n, size_of::<T>(), T::GL_TYPE, T::INTEGER));
         self
     }
 
@@ -67,15 +94,26 @@ impl VaoBuilder {
                 // The offset of the layer
                 let offset = sizes[..idx].iter().sum::<usize>();
 
-                // Set the vertex attribute pointer
-                gl::VertexAttribPointer(
-                    idx as u32,
-                    layer.0 as i32,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    stride as i32,
-                    offset as *const GLvoid,
-                );
+                // Set the vertex attribute pointer. Integers need their own
+                // function, or they are converted to floats
+                if layer.3 {
+                    gl::VertexAttribIPointer(
+                        idx as u32,
+                        layer.0 as i32,
+                        layer.2,
+                        stride as i32,
+                        offset as *const GLvoid,
+                    );
+                } else {
+                    gl::VertexAttribPointer(
+                        idx as u32,
+                        layer.0 as i32,
+                        layer.2,
+                        gl::FALSE,
+                        stride as i32,
+                        offset as *const GLvoid,
+                    );
+                }
 
                 // Enable the vertex attribute array
                 gl::EnableVertexAttribArray(idx as u32);
@@ -53,6 +53,13 @@ impl<T> Vbo<T> {
         }
     }
 
+    /// Deletes the VBO. It must not be used afterwards.
+    pub fn delete(&self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+
     /// Updates the VBO data.
     pub fn update(&self, verticies: &[T]) {
         let size = std::mem::size_of_val(verticies);
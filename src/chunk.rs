@@ -1,14 +1,141 @@
-use std::collections::HashMap;
+#![allow(dead_code)]
 
-use noise::NoiseFn;
+use std::mem::size_of;
 
 use crate::{
+    block_collections::{BlockCollection, BlockIndex},
     rendering::mesh::Mesh,
+    utils::chunk_to_world_position,
     voxel::{Voxel, VoxelKind},
 };
 
 pub const CHUNK_WIDTH: usize = 16;
-pub const CHUNK_HEIGHT: usize = 128;
+
+/// The height of a single vertical section of a chunk.
+pub const SECTION_HEIGHT: usize = 16;
+/// The number of sections stacked on top of each other in a chunk.
+pub const SECTIONS_PER_CHUNK: usize = 8;
+
+pub const CHUNK_HEIGHT: usize = SECTION_HEIGHT * SECTIONS_PER_CHUNK;
+
+/// The number of voxels in a single chunk section.
+pub const SECTION_VOLUME: usize = CHUNK_WIDTH * CHUNK_WIDTH * SECTION_HEIGHT;
+
+/// The voxels stored in a single section.
+#[derive(Debug, Clone)]
+pub enum SectionBlocks {
+    /// The section only contains air, and has no storage allocated.
+    Empty,
+    /// Every voxel in the section is the same, so no storage is allocated.
+    Uniform(Voxel),
+    /// The section contains different voxels.
+    Mixed(BlockCollection),
+}
+
+/// A 16x16x16 vertical slice of a chunk.
+#[derive(Debug, Clone)]
+pub struct ChunkSection {
+    /// The voxels in the section.
+    pub blocks: SectionBlocks,
+
+    /// Whether the section has changed since its mesh was last built.
+    pub dirty: bool,
+}
+
+impl ChunkSection {
+    /// Creates a new section, filled with air.
+    pub fn new() -> Self {
+        Self {
+            blocks: SectionBlocks::Empty,
+            dirty: true,
+        }
+    }
+
+    /// Returns true if the section only contains air.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.blocks, SectionBlocks::Empty)
+    }
+
+    /// Gets the voxel at the given position, relative to the section.
+    pub fn get(&self, pos: BlockIndex) -> Option<Voxel> {
+        if !BlockCollection::in_bounds(pos) {
+            return None;
+        }
+
+        match &self.blocks {
+            SectionBlocks::Empty => Some(VoxelKind::AIR.into()),
+            SectionBlocks::Uniform(voxel) => Some(*voxel),
+            SectionBlocks::Mixed(blocks) => blocks.get(pos),
+        }
+    }
+
+    /// Sets the voxel at the given position, relative to the section.
+    /// Storage is only allocated once the section stops being uniform.
+    pub fn set(&mut self, pos: BlockIndex, voxel: impl Into<Voxel>) {
+        let voxel = voxel.into();
+
+        if self.get(pos) == Some(voxel) {
+            return;
+        }
+
+        match &mut self.blocks {
+            SectionBlocks::Empty => {
+                let mut blocks = BlockCollection::new();
+                blocks.set(pos, voxel);
+                self.blocks = SectionBlocks::Mixed(blocks);
+            }
+            SectionBlocks::Uniform(uniform) => {
+                let mut blocks = BlockCollection::new_filled(*uniform);
+                blocks.set(pos, voxel);
+                self.blocks = SectionBlocks::Mixed(blocks);
+            }
+            SectionBlocks::Mixed(blocks) => blocks.set(pos, voxel),
+        }
+
+        self.dirty = true;
+    }
+
+    /// Sets every voxel in the section to the given voxel.
+    pub fn fill(&mut self, voxel: impl Into<Voxel>) {
+        let voxel = voxel.into();
+
+        self.blocks = if voxel.kind == VoxelKind::AIR {
+            SectionBlocks::Empty
+        } else {
+            SectionBlocks::Uniform(voxel)
+        };
+
+        self.dirty = true;
+    }
+
+    /// Compacts the section's storage, and drops it entirely if every voxel is the same.
+    pub fn optimize(&mut self) {
+        if let SectionBlocks::Mixed(blocks) = &mut self.blocks {
+            blocks.compact();
+
+            if blocks.palette_len() == 1 {
+                let voxel = blocks.get_by_index(0);
+                self.fill(voxel);
+            }
+        }
+    }
+
+    /// Returns the approximate number of bytes used by the section.
+    pub fn memory_usage(&self) -> usize {
+        match &self.blocks {
+            SectionBlocks::Mixed(blocks) => {
+                size_of::<Self>() - size_of::<BlockCollection>() + blocks.memory_usage()
+            }
+            _ => size_of::<Self>(),
+        }
+    }
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Represents a section of the world.
 #[derive(Debug)]
@@ -16,42 +143,238 @@ pub struct Chunk {
     /// The position of the chunk.
     pub position: (i32, i32),
 
-    /// The cubes in the chunk.
-    pub blocks: HashMap<(usize, usize, usize), Voxel>,
+    /// The vertical sections of the chunk, from the bottom up.
+    pub sections: [ChunkSection; SECTIONS_PER_CHUNK],
 
     /// The mesh of the chunk.
     pub mesh: Option<Mesh>,
+
+    /// Whether the chunk has been edited since it was generated or last saved.
+    pub modified: bool,
 }
 
 impl Chunk {
-    /// Creates a new chunk.
+    /// Creates a new chunk, filled with air.
     pub fn new(position: (i32, i32)) -> Self {
-        let mut cubes = HashMap::new();
-        cubes.reserve(CHUNK_WIDTH * CHUNK_WIDTH * CHUNK_HEIGHT);
-
-        for x in 0..CHUNK_WIDTH {
-            for z in 0..CHUNK_WIDTH {
-                for y in 0..CHUNK_HEIGHT {
-                    let true_x = x as i32 + position.0 * CHUNK_WIDTH as i32;
-                    let true_y = y as i32;
-                    let true_z = z as i32 + position.1 * CHUNK_WIDTH as i32;
-
-                    // cubes[get_chunk_index((x, y, z))].position = (true_x, true_y, true_z);
-                    cubes.insert(
-                        (x, y, z),
-                        Voxel {
-                            position: (true_x, true_y, true_z),
-                            kind: VoxelKind::Air,
-                        },
-                    );
+        Self {
+            position,
+            sections: std::array::from_fn(|_| ChunkSection::new()),
+            mesh: None,
+            modified: false,
+        }
+    }
+
+    /// Returns true if the position lies inside of the chunk.
+    pub fn in_bounds(pos: BlockIndex) -> bool {
+        let (x, y, z) = pos;
+
+        x < CHUNK_WIDTH && y < CHUNK_HEIGHT && z < CHUNK_WIDTH
+    }
+
+    /// Gets the voxel at the given position, relative to the chunk.
+    pub fn get(&self, pos: BlockIndex) -> Option<Voxel> {
+        if !Self::in_bounds(pos) {
+            return None;
+        }
+
+        let (x, y, z) = pos;
+        self.sections[y / SECTION_HEIGHT].get((x, y % SECTION_HEIGHT, z))
+    }
+
+    /// Sets the voxel at the given position, relative to the chunk.
+    /// Marks the section (and any section bordering the block) as dirty.
+    /// Panics if the position is outside of the chunk.
+    pub fn set(&mut self, pos: BlockIndex, voxel: impl Into<Voxel>) {
+        assert!(
+            Self::in_bounds(pos),
+            "Block position {:?} is outside of the chunk",
+            pos
+        );
+
+        let (x, y, z) = pos;
+        let section = y / SECTION_HEIGHT;
+        let local_y = y % SECTION_HEIGHT;
+
+        let voxel = voxel.into();
+        if self.sections[section].get((x, local_y, z)) == Some(voxel) {
+            return;
+        }
+
+        self.sections[section].set((x, local_y, z), voxel);
+
+        // Faces of the neighbouring section may have been hidden or exposed
+        if local_y == 0 && section > 0 {
+            self.sections[section - 1].dirty = true;
+        }
+        if local_y == SECTION_HEIGHT - 1 && section + 1 < SECTIONS_PER_CHUNK {
+            self.sections[section + 1].dirty = true;
+        }
+    }
+
+    /// Returns the height and voxel of the highest block in a column that isn't air.
+    pub fn surface(&self, x: usize, z: usize) -> Option<(usize, Voxel)> {
+        for (index, section) in self.sections.iter().enumerate().rev() {
+            if section.is_empty() {
+                continue;
+            }
+
+            for local_y in (0..SECTION_HEIGHT).rev() {
+                match section.get((x, local_y, z)) {
+                    Some(voxel) if voxel.kind != VoxelKind::AIR => {
+                        return Some((index * SECTION_HEIGHT + local_y, voxel));
+                    }
+                    _ => {}
                 }
             }
         }
 
-        Self {
-            position,
-            blocks: cubes,
-            mesh: None,
+        None
+    }
+
+    /// Sets every voxel in the chunk to the given voxel.
+    pub fn fill(&mut self, voxel: impl Into<Voxel>) {
+        let voxel = voxel.into();
+
+        for section in self.sections.iter_mut() {
+            section.fill(voxel);
+        }
+    }
+
+    /// Returns the indices of all sections whose meshes need to be rebuilt.
+    pub fn dirty_sections(&self) -> impl Iterator<Item = usize> + '_ {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.dirty)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns true if any section of the chunk needs to be remeshed.
+    pub fn needs_remesh(&self) -> bool {
+        self.sections.iter().any(|section| section.dirty)
+    }
+
+    /// Marks every section as having an up to date mesh.
+    pub fn mark_clean(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.dirty = false;
         }
     }
+
+    /// Compacts the storage of every section.
+    pub fn optimize(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.optimize();
+        }
+    }
+
+    /// Returns the world position of a block in this chunk.
+    pub fn world_position(&self, pos: BlockIndex) -> (i32, i32, i32) {
+        chunk_to_world_position(self.position, pos)
+    }
+
+    /// Returns the approximate number of bytes used by the chunk's blocks.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() - size_of::<[ChunkSection; SECTIONS_PER_CHUNK]>()
+            + self
+                .sections
+                .iter()
+                .map(ChunkSection::memory_usage)
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_only_store_blocks_once_they_differ() {
+        let stone = VoxelKind(1);
+        let dirt = VoxelKind(2);
+
+        let mut section = ChunkSection::new();
+        assert!(section.is_empty());
+        assert_eq!(section.get((3, 4, 5)), Some(VoxelKind::AIR.into()));
+
+        // Writing air into an empty section doesn't allocate anything
+        section.set((3, 4, 5), VoxelKind::AIR);
+        assert!(matches!(section.blocks, SectionBlocks::Empty));
+
+        section.fill(stone);
+        assert!(matches!(section.blocks, SectionBlocks::Uniform(voxel) if voxel == stone.into()));
+
+        section.set((3, 4, 5), stone);
+        assert!(matches!(section.blocks, SectionBlocks::Uniform(_)));
+
+        // A different block makes it mixed, keeping the rest of the section
+        section.set((3, 4, 5), dirt);
+        match &section.blocks {
+            SectionBlocks::Mixed(blocks) => assert_eq!(blocks.palette_len(), 2),
+            blocks => panic!("Expected a mixed section, found {:?}", blocks),
+        }
+        assert_eq!(section.get((3, 4, 5)), Some(dirt.into()));
+        assert_eq!(section.get((0, 0, 0)), Some(stone.into()));
+
+        // Optimizing keeps mixed sections mixed
+        section.optimize();
+        assert!(matches!(section.blocks, SectionBlocks::Mixed(_)));
+
+        // and turns sections that became uniform back into a single voxel
+        section.set((3, 4, 5), stone);
+        assert!(matches!(section.blocks, SectionBlocks::Mixed(_)));
+        section.optimize();
+        assert!(matches!(section.blocks, SectionBlocks::Uniform(voxel) if voxel == stone.into()));
+
+        section.fill(VoxelKind::AIR);
+        assert!(section.is_empty());
+    }
+
+    #[test]
+    fn sections_of_only_air_are_emptied_when_optimized() {
+        let mut section = ChunkSection::new();
+        section.set((1, 1, 1), VoxelKind(1));
+        assert!(!section.is_empty());
+
+        section.set((1, 1, 1), VoxelKind::AIR);
+        section.optimize();
+
+        assert!(section.is_empty());
+        assert_eq!(section.memory_usage(), size_of::<ChunkSection>());
+    }
+
+    #[test]
+    fn chunks_split_blocks_across_sections() {
+        let mut chunk = Chunk::new((0, 0));
+        chunk.mark_clean();
+
+        chunk.set((1, 17, 2), VoxelKind(1));
+
+        assert_eq!(chunk.get((1, 17, 2)), Some(VoxelKind(1).into()));
+        assert_eq!(chunk.sections[1].get((1, 1, 2)), Some(VoxelKind(1).into()));
+        assert!(chunk
+            .sections
+            .iter()
+            .enumerate()
+            .all(|(index, section)| section.is_empty() == (index != 1)));
+        assert_eq!(chunk.dirty_sections().collect::<Vec<_>>(), [1]);
+
+        // Blocks on the border of a section also dirty the section they touch
+        chunk.mark_clean();
+        chunk.set((0, 32, 0), VoxelKind(2));
+        assert_eq!(chunk.dirty_sections().collect::<Vec<_>>(), [1, 2]);
+
+        chunk.mark_clean();
+        chunk.set((0, 47, 0), VoxelKind(2));
+        assert_eq!(chunk.dirty_sections().collect::<Vec<_>>(), [2, 3]);
+
+        // Writing the block that is already there changes nothing
+        chunk.mark_clean();
+        chunk.set((0, 47, 0), VoxelKind(2));
+        assert!(!chunk.needs_remesh());
+
+        assert_eq!(chunk.get((0, CHUNK_HEIGHT, 0)), None);
+        assert_eq!(chunk.surface(0, 0), Some((47, VoxelKind(2).into())));
+        assert_eq!(chunk.surface(5, 5), None);
+    }
 }
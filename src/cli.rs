@@ -0,0 +1,282 @@
+use crate::{
+    config::{Config, ConfigTable, ConfigValue},
+    formats::{map::MapOptions, selection::Selection, MeshFormat},
+    rendering::mesh::MeshingMode,
+};
+
+/// The usage shown when the command line can't be parsed.
+pub const USAGE: &str = "Usage: [world directory] [--export-mesh <path>] [--export-map <path>] \
+[--map-chunks <min_x>,<min_z>,<max_x>,<max_z>] [--map-grid] [--meshing <naive|greedy>] \
+[--export-vox <path>] [--export-schem <path>] [--select <x0>,<y0>,<z0>,<x1>,<y1>,<z1>] \
+[--seed <seed>] [--generator <name>] [--param <key>=<value>]...";
+
+/// The options given on the command line.
+#[derive(Debug, Clone)]
+pub struct CommandLine {
+    /// The directory of the world to open.
+    pub world_path: String,
+
+    /// Where to write the mesh of the world, and in which format, instead
+    /// of opening a window.
+    pub export_mesh: Option<(String, MeshFormat)>,
+
+    /// Where to write a map of the world, instead of opening a window.
+    pub export_map: Option<String>,
+
+    /// The chunks drawn on the map.
+    pub map_options: MapOptions,
+
+    /// How chunk meshes are built.
+    pub meshing: MeshingMode,
+
+    /// Where to write the selected blocks as a MagicaVoxel model.
+    pub export_vox: Option<String>,
+
+    /// Where to write the selected blocks as a Sponge schematic.
+    pub export_schem: Option<String>,
+
+    /// The blocks written by `--export-vox` and `--export-schem`.
+    pub selection: Option<Selection>,
+
+    /// The seed of the terrain generator.
+    pub seed: Option<u32>,
+
+    /// The name of the terrain generator.
+    pub generator: Option<String>,
+
+    /// The parameters of the terrain generator.
+    pub generator_params: ConfigTable,
+}
+
+impl CommandLine {
+    /// Parses the arguments, not including the name of the program.
+    pub fn parse(
+        mut args: impl Iterator<Item = String>,
+        default_world_path: &str,
+    ) -> Result<Self, String> {
+        let mut command_line = Self {
+            world_path: default_world_path.to_string(),
+            export_mesh: None,
+            export_map: None,
+            map_options: MapOptions::default(),
+            meshing: MeshingMode::Greedy,
+            export_vox: None,
+            export_schem: None,
+            selection: None,
+            seed: None,
+            generator: None,
+            generator_params: ConfigTable::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Expected a value after '{}'", arg))
+            };
+
+            match arg.as_str() {
+                "--export-mesh" => {
+                    let path = value()?;
+
+                    match MeshFormat::from_path(&path) {
+                        Some(format) => command_line.export_mesh = Some((path, format)),
+                        None => {
+                            return Err(format!(
+                                "Can only export meshes to .obj or .glb files, not '{}'",
+                                path
+                            ))
+                        }
+                    }
+                }
+                "--export-map" => command_line.export_map = Some(value()?),
+                "--map-chunks" => {
+                    let bounds = value()?
+                        .split(',')
+                        .map(|bound| bound.trim().parse::<i32>().ok())
+                        .collect::<Option<Vec<_>>>();
+
+                    match bounds.as_deref() {
+                        Some([min_x, min_z, max_x, max_z]) => {
+                            command_line.map_options.min_chunk = (*min_x, *min_z);
+                            command_line.map_options.max_chunk = (*max_x, *max_z);
+                        }
+                        _ => {
+                            return Err(
+                                "'--map-chunks' expects four comma separated integers".to_string()
+                            )
+                        }
+                    }
+                }
+                "--map-grid" => command_line.map_options.grid_lines = true,
+                "--meshing" => {
+                    let mode = value()?;
+
+                    command_line.meshing = MeshingMode::from_name(&mode)
+                        .ok_or_else(|| format!("Unknown meshing mode '{}'", mode))?;
+                }
+                "--export-vox" => command_line.export_vox = Some(value()?),
+                "--export-schem" => command_line.export_schem = Some(value()?),
+                "--select" => {
+                    let corners = value()?
+                        .split(',')
+                        .map(|coordinate| coordinate.trim().parse::<i32>().ok())
+                        .collect::<Option<Vec<_>>>();
+
+                    match corners.as_deref() {
+                        Some([x0, y0, z0, x1, y1, z1]) => {
+                            command_line.selection =
+                                Some(Selection::new((*x0, *y0, *z0), (*x1, *y1, *z1)));
+                        }
+                        _ => {
+                            return Err(
+                                "'--select' expects six comma separated integers".to_string()
+                            )
+                        }
+                    }
+                }
+                "--seed" => {
+                    let seed = value()?;
+
+                    command_line.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("Invalid seed '{}'", seed))?,
+                    );
+                }
+                "--generator" => command_line.generator = Some(value()?),
+                "--param" => {
+                    let param = value()?;
+                    let (key, value) = param
+                        .split_once('=')
+                        .ok_or("'--param' expects a <key>=<value> pair")?;
+
+                    command_line
+                        .generator_params
+                        .insert(key.trim(), parse_param(value.trim()));
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'", arg)),
+                _ => command_line.world_path = arg,
+            }
+        }
+
+        let exports_selection =
+            command_line.export_vox.is_some() || command_line.export_schem.is_some();
+
+        if exports_selection && command_line.selection.is_none() {
+            return Err("'--export-vox' and '--export-schem' need a '--select' box".to_string());
+        }
+
+        if !exports_selection && command_line.selection.is_some() {
+            return Err("'--select' needs '--export-vox' or '--export-schem'".to_string());
+        }
+
+        Ok(command_line)
+    }
+
+    /// Returns true if the terrain generator was chosen on the command line.
+    pub fn chooses_terrain(&self) -> bool {
+        self.seed.is_some() || self.generator.is_some() || !self.generator_params.is_empty()
+    }
+
+    /// Returns true if anything is exported, instead of opening a window.
+    pub fn exports(&self) -> bool {
+        self.export_map.is_some()
+            || self.export_mesh.is_some()
+            || self.export_vox.is_some()
+            || self.export_schem.is_some()
+    }
+}
+
+/// Parses the value of a generator parameter, which is written like a value
+/// in a config file. Anything else is taken as a string, so that names and
+/// paths don't need quotes.
+fn parse_param(value: &str) -> ConfigValue {
+    match Config::parse(&format!("value = {}", value)) {
+        Ok(config) => match config.root.get("value") {
+            Some(value) => value.clone(),
+            None => ConfigValue::from(value),
+        },
+        Err(_) => ConfigValue::from(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CommandLine, String> {
+        CommandLine::parse(args.iter().map(|arg| arg.to_string()), "world")
+    }
+
+    #[test]
+    fn selections_are_parsed_in_any_order() {
+        let command_line = parse(&["--select", "5,20,-3,-1,10,4", "--export-schem", "a.schem"]);
+        let command_line = command_line.unwrap();
+
+        assert_eq!(command_line.export_schem.as_deref(), Some("a.schem"));
+        assert_eq!(
+            command_line.selection,
+            Some(Selection::new((-1, 10, -3), (5, 20, 4)))
+        );
+    }
+
+    #[test]
+    fn meshes_are_only_exported_to_known_formats() {
+        let command_line = parse(&["--export-mesh", "world.glb"]).unwrap();
+        assert_eq!(
+            command_line.export_mesh,
+            Some(("world.glb".to_string(), MeshFormat::Glb))
+        );
+
+        let command_line = parse(&["--export-mesh", "world.obj"]).unwrap();
+        assert_eq!(
+            command_line.export_mesh,
+            Some(("world.obj".to_string(), MeshFormat::Obj))
+        );
+
+        assert!(parse(&["--export-mesh", "world.stl"]).is_err());
+        assert!(parse(&["--export-mesh", "world"]).is_err());
+    }
+
+    #[test]
+    fn generators_are_chosen_with_their_params() {
+        let command_line = parse(&[
+            "--export-map",
+            "map.png",
+            "--seed",
+            "42",
+            "--generator",
+            "flat_plane",
+            "--param",
+            "block=dirt",
+            "--param",
+            "height = 12",
+            "--param",
+            "offsets=[1, 2]",
+        ])
+        .unwrap();
+
+        assert!(command_line.chooses_terrain() && command_line.exports());
+        assert_eq!(command_line.seed, Some(42));
+        assert_eq!(command_line.generator.as_deref(), Some("flat_plane"));
+
+        let params = &command_line.generator_params;
+        assert_eq!(params.get_str("block"), Some("dirt"));
+        assert_eq!(params.get_integer("height"), Some(12));
+        assert_eq!(params.get_floats("offsets"), Some(vec![1.0, 2.0]));
+
+        assert!(!parse(&[]).unwrap().chooses_terrain());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--param", "height"]).is_err());
+    }
+
+    #[test]
+    fn selection_exports_need_a_selection() {
+        assert!(parse(&["--export-vox", "a.vox"]).is_err());
+        assert!(parse(&["--export-vox", "a.vox", "--select", "1,2,3"]).is_err());
+
+        // A selection on its own wouldn't export anything
+        assert!(parse(&["--select", "1,2,3,4,5,6"]).is_err());
+        assert!(parse(&["--export-map", "a.png", "--select", "1,2,3,4,5,6"]).is_err());
+        assert!(!parse(&["--meshing", "naive"]).unwrap().exports());
+    }
+}
@@ -0,0 +1,383 @@
+#![allow(dead_code)]
+
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+/// A single value in a config file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<ConfigValue>),
+}
+
+/// A set of key-value pairs in a config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigTable(BTreeMap<String, ConfigValue>);
+
+/// A parsed TOML config file. Tables and arrays of tables may only be
+/// declared at the top level, and values are booleans, integers, floats,
+/// strings, or arrays of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// The keys that appear before any table header.
+    pub root: ConfigTable,
+
+    /// The tables declared with `[name]`.
+    pub tables: BTreeMap<String, ConfigTable>,
+
+    /// The arrays of tables declared with `[[name]]`.
+    pub arrays: BTreeMap<String, Vec<ConfigTable>>,
+}
+
+impl ConfigValue {
+    /// Returns the value as a boolean, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ConfigValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an integer, if it is one.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            ConfigValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float. Integers are converted.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            ConfigValue::Float(value) => Some(*value),
+            ConfigValue::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ConfigValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an array, if it is one.
+    pub fn as_array(&self) -> Option<&[ConfigValue]> {
+        match self {
+            ConfigValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Converts a TOML value. Tables and dates aren't supported as values.
+    fn from_toml(value: toml::Value) -> Result<Self, String> {
+        match value {
+            toml::Value::Boolean(value) => Ok(ConfigValue::Bool(value)),
+            toml::Value::Integer(value) => Ok(ConfigValue::Integer(value)),
+            toml::Value::Float(value) => Ok(ConfigValue::Float(value)),
+            toml::Value::String(value) => Ok(ConfigValue::String(value)),
+            toml::Value::Array(values) => values
+                .into_iter()
+                .map(ConfigValue::from_toml)
+                .collect::<Result<_, _>>()
+                .map(ConfigValue::Array),
+            toml::Value::Table(_) => Err("Nested tables aren't supported".to_string()),
+            toml::Value::Datetime(_) => Err("Dates aren't supported".to_string()),
+        }
+    }
+
+    /// Converts the value into a TOML value.
+    fn to_toml(&self) -> toml::Value {
+        match self {
+            ConfigValue::Bool(value) => toml::Value::Boolean(*value),
+            ConfigValue::Integer(value) => toml::Value::Integer(*value),
+            ConfigValue::Float(value) => toml::Value::Float(*value),
+            ConfigValue::String(value) => toml::Value::String(value.clone()),
+            ConfigValue::Array(values) => {
+                toml::Value::Array(values.iter().map(ConfigValue::to_toml).collect())
+            }
+        }
+    }
+}
+
+impl ConfigTable {
+    /// Creates a new, empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the value of a key.
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.0.get(key)
+    }
+
+    /// Sets the value of a key.
+    pub fn insert(&mut self, key: &str, value: impl Into<ConfigValue>) {
+        self.0.insert(key.to_string(), value.into());
+    }
+
+    /// Removes a key, returning its value.
+    pub fn remove(&mut self, key: &str) -> Option<ConfigValue> {
+        self.0.remove(key)
+    }
+
+    /// Returns true if the table has the given key.
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Returns true if the table doesn't have any keys.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over all key-value pairs, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ConfigValue)> {
+        self.0.iter()
+    }
+
+    /// Gets a boolean value.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(ConfigValue::as_bool)
+    }
+
+    /// Gets an integer value.
+    pub fn get_integer(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(ConfigValue::as_integer)
+    }
+
+    /// Gets a float value.
+    pub fn get_float(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(ConfigValue::as_float)
+    }
+
+    /// Gets a string value.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(ConfigValue::as_str)
+    }
+
+    /// Gets an array of floats.
+    pub fn get_floats(&self, key: &str) -> Option<Vec<f64>> {
+        self.get(key)?
+            .as_array()?
+            .iter()
+            .map(ConfigValue::as_float)
+            .collect()
+    }
+
+    /// Gets an array of strings.
+    pub fn get_strs(&self, key: &str) -> Option<Vec<&str>> {
+        self.get(key)?
+            .as_array()?
+            .iter()
+            .map(ConfigValue::as_str)
+            .collect()
+    }
+
+    /// Converts a TOML table, which may only hold plain values.
+    fn from_toml(table: toml::Table) -> Result<Self, String> {
+        let mut result = ConfigTable::new();
+
+        for (key, value) in table {
+            let value =
+                ConfigValue::from_toml(value).map_err(|err| format!("Key '{}': {}", key, err))?;
+            result.0.insert(key, value);
+        }
+
+        Ok(result)
+    }
+
+    /// Converts the table into a TOML table.
+    fn to_toml(&self) -> toml::Table {
+        self.iter()
+            .map(|(key, value)| (key.clone(), value.to_toml()))
+            .collect()
+    }
+}
+
+impl Config {
+    /// Reads and parses a config file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read '{}': {}", path.display(), err))?;
+
+        Self::parse(&source).map_err(|err| format!("{} (in '{}')", err, path.display()))
+    }
+
+    /// Writes the config to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+
+        fs::write(path, self.to_string())
+            .map_err(|err| format!("Failed to write '{}': {}", path.display(), err))
+    }
+
+    /// Parses a config from its source text.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let document = source
+            .parse::<toml::Table>()
+            .map_err(|err| err.to_string())?;
+
+        let mut config = Config::default();
+
+        for (key, value) in document {
+            match value {
+                toml::Value::Table(table) => {
+                    config.tables.insert(key, ConfigTable::from_toml(table)?);
+                }
+                toml::Value::Array(values)
+                    if !values.is_empty() && values.iter().all(toml::Value::is_table) =>
+                {
+                    let tables = values
+                        .into_iter()
+                        .filter_map(|value| match value {
+                            toml::Value::Table(table) => Some(ConfigTable::from_toml(table)),
+                            _ => None,
+                        })
+                        .collect::<Result<_, _>>()?;
+
+                    config.arrays.insert(key, tables);
+                }
+                value => {
+                    let value = ConfigValue::from_toml(value)
+                        .map_err(|err| format!("Key '{}': {}", key, err))?;
+                    config.root.0.insert(key, value);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Converts the config into a TOML document.
+    fn to_toml(&self) -> toml::Table {
+        let mut document = self.root.to_toml();
+
+        for (name, table) in self.tables.iter() {
+            document.insert(name.clone(), toml::Value::Table(table.to_toml()));
+        }
+
+        for (name, tables) in self.arrays.iter() {
+            let tables = tables
+                .iter()
+                .map(|table| toml::Value::Table(table.to_toml()))
+                .collect();
+
+            document.insert(name.clone(), toml::Value::Array(tables));
+        }
+
+        document
+    }
+}
+
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_toml())
+    }
+}
+
+impl fmt::Display for ConfigTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_toml())
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_toml())
+    }
+}
+
+impl From<bool> for ConfigValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for ConfigValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<f64> for ConfigValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<f32> for ConfigValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value as f64)
+    }
+}
+
+impl From<&str> for ConfigValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for ConfigValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl<T: Into<ConfigValue>> From<Vec<T>> for ConfigValue {
+    fn from(values: Vec<T>) -> Self {
+        Self::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configs_round_trip_through_text() {
+        let mut config = Config::default();
+        config.root.insert("seed", 42i64);
+        config.root.insert("name", "a \"quoted\" name");
+
+        let mut table = ConfigTable::new();
+        table.insert("scale", 0.5);
+        table.insert("blocks", vec!["stone", "dirt"]);
+        config.tables.insert("generator".to_string(), table);
+
+        let mut entry = ConfigTable::new();
+        entry.insert("enabled", true);
+        entry.insert("offsets", vec![1i64, -2, 3]);
+        config
+            .arrays
+            .insert("entries".to_string(), vec![entry.clone(), entry]);
+
+        assert_eq!(Config::parse(&config.to_string()), Ok(config));
+    }
+
+    #[test]
+    fn nested_tables_are_rejected() {
+        assert!(Config::parse("[a.b]\nc = 1").is_err());
+        assert!(Config::parse("[a]\nb = { c = 1 }").is_err());
+    }
+
+    #[test]
+    fn bundled_assets_parse() {
+        for path in [
+            "assets/blocks.toml",
+            "assets/biomes.toml",
+            "assets/ores.toml",
+            "assets/vox_palette.toml",
+        ] {
+            if let Err(err) = Config::load(path) {
+                panic!("{}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+#![allow(dead_code)]
+
+use std::{fmt::Write as _, io, path::Path};
+
+use crate::{
+    formats::obj::material_name,
+    registry::{block_registry, MAX_LIGHT_LEVEL},
+    rendering::mesh::Mesh,
+};
+
+/// The magic bytes at the start of every .glb file.
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+/// The version of the binary glTF container.
+const GLB_VERSION: u32 = 2;
+
+/// The type of the chunk holding the JSON document.
+const CHUNK_JSON: &[u8; 4] = b"JSON";
+/// The type of the chunk holding the binary buffer.
+const CHUNK_BIN: &[u8; 4] = b"BIN\0";
+
+/// The component type of 32-bit floats.
+const COMPONENT_FLOAT: u32 = 5126;
+/// The component type of 32-bit unsigned integers.
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+
+/// The buffer view target for vertex attributes.
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+/// The buffer view target for indices.
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Writes a mesh to a binary glTF (.glb) file.
+pub fn save_glb(mesh: &Mesh, path: impl AsRef<Path>) -> io::Result<()> {
+    std::fs::write(path, write_glb(mesh))
+}
+
+/// Encodes a mesh as binary glTF 2.0.
+///
+/// The buffer holds the positions, normals and texture coordinates of every
+/// vertex, followed by the indices of each block type. Every block type
+/// becomes a primitive with its own material, coloured by the block's tint.
+pub fn write_glb(mesh: &Mesh) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut primitives = Vec::new();
+    let mut materials = Vec::new();
+
+    if !mesh.vertices.is_empty() {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+
+        for vertex in mesh.vertices.iter() {
+            let (x, y, z) = vertex.position;
+
+            for (axis, value) in [x, y, z].into_iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+
+        let positions = mesh.vertices.iter().flat_map(|vertex| {
+            let (x, y, z) = vertex.position;
+            [x, y, z]
+        });
+        buffer_views.push(push_view(&mut buffer, positions, TARGET_ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":0,"componentType":{},"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            COMPONENT_FLOAT,
+            mesh.vertices.len(),
+            min[0],
+            min[1],
+            min[2],
+            max[0],
+            max[1],
+            max[2]
+        ));
+
+        let normals = mesh.vertices.iter().flat_map(|vertex| {
+            let (x, y, z) = vertex.normal();
+            [x, y, z]
+        });
+        buffer_views.push(push_view(&mut buffer, normals, TARGET_ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":1,"componentType":{},"count":{},"type":"VEC3"}}"#,
+            COMPONENT_FLOAT,
+            mesh.vertices.len()
+        ));
+
+        // glTF texture coordinates start at the top of the image, not the bottom
+        let uvs = mesh.vertices.iter().flat_map(|vertex| {
+            let (u, v) = vertex.uv();
+            [u, 1.0 - v]
+        });
+        buffer_views.push(push_view(&mut buffer, uvs, TARGET_ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":2,"componentType":{},"count":{},"type":"VEC2"}}"#,
+            COMPONENT_FLOAT,
+            mesh.vertices.len()
+        ));
+
+        for (kind, indices) in mesh.indices_by_kind() {
+            let view = buffer_views.len();
+            buffer_views.push(push_view(
+                &mut buffer,
+                indices.iter().copied(),
+                TARGET_ELEMENT_ARRAY_BUFFER,
+            ));
+
+            let accessor = accessors.len();
+            accessors.push(format!(
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+                view,
+                COMPONENT_UNSIGNED_INT,
+                indices.len()
+            ));
+
+            primitives.push(format!(
+                r#"{{"attributes":{{"POSITION":0,"NORMAL":1,"TEXCOORD_0":2}},"indices":{},"material":{},"mode":4}}"#,
+                accessor,
+                materials.len()
+            ));
+
+            let (tint, emission) = match block_registry().get(kind) {
+                Some(block) => (
+                    block.tint,
+                    block.light_emission as f32 / MAX_LIGHT_LEVEL as f32,
+                ),
+                None => ((1.0, 0.0, 1.0), 0.0),
+            };
+
+            // Faces aren't consistently wound, so they can't be culled
+            materials.push(format!(
+                r#"{{"name":"{}","doubleSided":true,"pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},1],"metallicFactor":0,"roughnessFactor":1}},"emissiveFactor":[{},{},{}]}}"#,
+                escape_json(&material_name(kind)),
+                tint.0,
+                tint.1,
+                tint.2,
+                emission,
+                emission,
+                emission
+            ));
+        }
+    }
+
+    let mut json = String::new();
+    json.push_str(r#"{"asset":{"version":"2.0","generator":"VoxelEngine"},"scene":0,"scenes":[{"nodes":[0]}],"#);
+
+    if primitives.is_empty() {
+        json.push_str(r#""nodes":[{"name":"terrain"}]"#);
+    } else {
+        let _ = write!(
+            json,
+            r#""nodes":[{{"name":"terrain","mesh":0}}],"meshes":[{{"primitives":[{}]}}],"materials":[{}],"buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}]"#,
+            primitives.join(","),
+            materials.join(","),
+            buffer.len(),
+            buffer_views.join(","),
+            accessors.join(",")
+        );
+    }
+
+    json.push('}');
+
+    // Chunks must be aligned to 4 bytes, JSON with spaces and binary with zeros
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+    let mut length = 12 + 8 + json.len();
+    if !buffer.is_empty() {
+        length += 8 + buffer.len();
+    }
+
+    let mut bytes = Vec::with_capacity(length);
+    bytes.extend_from_slice(GLB_MAGIC);
+    bytes.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(length as u32).to_le_bytes());
+
+    bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(CHUNK_JSON);
+    bytes.extend_from_slice(&json);
+
+    if !buffer.is_empty() {
+        bytes.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(CHUNK_BIN);
+        bytes.extend_from_slice(&buffer);
+    }
+
+    bytes
+}
+
+/// A value that can be written to the binary buffer.
+trait BufferValue: Copy {
+    fn write(self, buffer: &mut Vec<u8>);
+}
+
+impl BufferValue for f32 {
+    fn write(self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl BufferValue for u32 {
+    fn write(self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// Appends values to the buffer, and returns the JSON of a buffer view over them.
+fn push_view<T: BufferValue>(
+    buffer: &mut Vec<u8>,
+    values: impl Iterator<Item = T>,
+    target: u32,
+) -> String {
+    let offset = buffer.len();
+
+    for value in values {
+        value.write(buffer);
+    }
+
+    format!(
+        r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+        offset,
+        buffer.len() - offset,
+        target
+    )
+}
+
+/// Escapes the characters of a string that can't appear in a JSON string.
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::Chunk,
+        rendering::mesh::{MeshBuilder, MeshingMode},
+        world::World,
+    };
+
+    /// Reads a little endian integer at the given offset.
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn glb_files_have_a_json_and_binary_chunk() {
+        crate::init_test_globals();
+
+        let mut world = World::new();
+        world.insert(Chunk::new((0, 0)));
+        world.set_block(3, 10, 4, block_registry().kind("stone"));
+        world.set_block(6, 10, 4, block_registry().kind("dirt"));
+
+        let mesh = MeshBuilder::with_mode(MeshingMode::Naive).build(&world);
+        let bytes = write_glb(&mesh);
+
+        // The header
+        assert_eq!(&bytes[0..4], GLB_MAGIC);
+        assert_eq!(read_u32(&bytes, 4), GLB_VERSION);
+        assert_eq!(read_u32(&bytes, 8) as usize, bytes.len());
+
+        // The JSON chunk, padded to 4 bytes
+        let json_length = read_u32(&bytes, 12) as usize;
+        assert_eq!(&bytes[16..20], CHUNK_JSON);
+        assert_eq!(json_length % 4, 0);
+
+        let json = std::str::from_utf8(&bytes[20..20 + json_length]).unwrap();
+        assert!(json.contains(r#""POSITION":0,"NORMAL":1,"TEXCOORD_0":2"#));
+        assert_eq!(json.matches(r#""mode":4"#).count(), 2);
+
+        // The binary chunk holds the positions, normals and UVs of 48
+        // vertices, and the indices of 24 triangles
+        let bin_start = 20 + json_length;
+        let bin_length = read_u32(&bytes, bin_start) as usize;
+        assert_eq!(&bytes[bin_start + 4..bin_start + 8], CHUNK_BIN);
+        assert_eq!(bin_length, 48 * 12 + 48 * 12 + 48 * 8 + 24 * 3 * 4);
+        assert_eq!(bin_start + 8 + bin_length, bytes.len());
+        assert!(json.contains(&format!(r#""buffers":[{{"byteLength":{}}}]"#, bin_length)));
+
+        for (count, kind) in [(48, "VEC3"), (48, "VEC2"), (36, "SCALAR")] {
+            let accessor = format!(r#""count":{},"type":"{}""#, count, kind);
+            assert!(json.contains(&accessor), "{}", accessor);
+        }
+    }
+
+    #[test]
+    fn empty_meshes_have_no_binary_chunk() {
+        let mesh = MeshBuilder::with_mode(MeshingMode::Naive).build(&World::new());
+        let bytes = write_glb(&mesh);
+
+        assert_eq!(read_u32(&bytes, 8) as usize, bytes.len());
+        assert_eq!(20 + read_u32(&bytes, 12) as usize, bytes.len());
+    }
+}
@@ -0,0 +1,190 @@
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use image::{Rgb, RgbImage};
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    registry::block_registry,
+    systems::generators::{GenContext, TerrainGenerator},
+};
+
+/// How much darker the lowest blocks are drawn than the highest ones.
+const MIN_SHADE: f32 = 0.35;
+
+/// How much the colour of a pixel on a chunk border is darkened by grid lines.
+const GRID_SHADE: f32 = 0.6;
+
+/// The chunks drawn on a map, and how they are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapOptions {
+    /// The chunk with the lowest coordinates.
+    pub min_chunk: (i32, i32),
+    /// The chunk with the highest coordinates (inclusive).
+    pub max_chunk: (i32, i32),
+    /// Whether the borders between chunks are darkened.
+    pub grid_lines: bool,
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        Self {
+            min_chunk: (-8, -8),
+            max_chunk: (7, 7),
+            grid_lines: false,
+        }
+    }
+}
+
+/// Generates a rectangle of chunks and draws them from above, one pixel per
+/// column. The image's x axis is the world's x axis, and its y axis is the
+/// world's z axis. Each pixel is the tint of the column's highest block,
+/// shaded by its height. Empty columns are black. Decorations are only drawn
+/// in the chunk they were generated in.
+pub fn render_map(generator: &dyn TerrainGenerator, options: MapOptions) -> RgbImage {
+    let ctx = GenContext::new(block_registry());
+
+    let (min_x, min_z) = options.min_chunk;
+    let (max_x, max_z) = options.max_chunk;
+
+    let chunks_x = (max_x - min_x + 1).max(0) as u32;
+    let chunks_z = (max_z - min_z + 1).max(0) as u32;
+
+    let mut image = RgbImage::new(chunks_x * CHUNK_WIDTH as u32, chunks_z * CHUNK_WIDTH as u32);
+
+    for chunk_x in min_x..=max_x {
+        for chunk_z in min_z..=max_z {
+            let mut chunk = Chunk::new((chunk_x, chunk_z));
+            generator.generate(&mut chunk, &ctx);
+            generator.decorate(&mut chunk, &ctx);
+
+            let image_x = (chunk_x - min_x) as u32 * CHUNK_WIDTH as u32;
+            let image_y = (chunk_z - min_z) as u32 * CHUNK_WIDTH as u32;
+
+            for x in 0..CHUNK_WIDTH {
+                for z in 0..CHUNK_WIDTH {
+                    let mut color = column_color(&chunk, x, z);
+
+                    if options.grid_lines && (x == 0 || z == 0) {
+                        color = color.map(|c| c * GRID_SHADE);
+                    }
+
+                    let pixel = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                    image.put_pixel(image_x + x as u32, image_y + z as u32, Rgb(pixel));
+                }
+            }
+        }
+    }
+
+    image
+}
+
+/// Draws a map of generated chunks and writes it to a PNG file.
+pub fn save_map(
+    generator: &dyn TerrainGenerator,
+    options: MapOptions,
+    path: impl AsRef<Path>,
+) -> Result<(), String> {
+    render_map(generator, options)
+        .save(path)
+        .map_err(|err| err.to_string())
+}
+
+/// Returns the colour of a column, from its highest block and its height.
+fn column_color(chunk: &Chunk, x: usize, z: usize) -> [f32; 3] {
+    let (height, voxel) = match chunk.surface(x, z) {
+        Some(surface) => surface,
+        None => return [0.0; 3],
+    };
+
+    let (r, g, b) = block_registry()
+        .get(voxel.kind)
+        .map_or((1.0, 0.0, 1.0), |block| block.tint);
+
+    let shade = MIN_SHADE + (1.0 - MIN_SHADE) * (height + 1) as f32 / CHUNK_HEIGHT as f32;
+
+    [r * shade, g * shade, b * shade]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ConfigTable, systems::generators::generator_registry};
+
+    #[test]
+    fn maps_show_the_surface_of_each_column() {
+        crate::init_test_globals();
+
+        let mut params = ConfigTable::new();
+        params.insert("block", "dirt");
+        params.insert("height", 12i64);
+
+        let generator = generator_registry()
+            .create("flat_plane", 7, &params)
+            .unwrap();
+
+        let options = MapOptions {
+            min_chunk: (-1, -1),
+            max_chunk: (1, 0),
+            grid_lines: true,
+        };
+        let image = render_map(generator.as_ref(), options);
+
+        // Dirt's tint, shaded by the height of the plane
+        let dirt = Rgb([58, 42, 30]);
+        let grid = Rgb([35, 25, 18]);
+
+        assert_eq!(image.dimensions(), (48, 32));
+        assert_eq!(*image.get_pixel(5, 7), dirt);
+        assert_eq!(*image.get_pixel(47, 31), dirt);
+
+        // Grid lines run along the first row and column of every chunk
+        assert_eq!(*image.get_pixel(16, 7), grid);
+        assert_eq!(*image.get_pixel(5, 16), grid);
+        assert_eq!(*image.get_pixel(0, 0), grid);
+    }
+
+    #[test]
+    fn maps_of_a_seed_are_the_same_every_time() {
+        crate::init_test_globals();
+
+        let generator = generator_registry()
+            .create("perlin_2d", 42, &ConfigTable::new())
+            .unwrap();
+
+        let options = MapOptions {
+            min_chunk: (-2, -1),
+            max_chunk: (1, 0),
+            grid_lines: false,
+        };
+        let image = render_map(generator.as_ref(), options);
+
+        assert_eq!(image.dimensions(), (64, 32));
+        assert_eq!(image, render_map(generator.as_ref(), options));
+
+        // Every column is grass, so the pixels only differ by their height
+        let ctx = GenContext::new(block_registry());
+        let grass = block_registry().kind("grass");
+
+        for (chunk_x, chunk_z) in [(-2, -1), (1, 0)] {
+            let mut chunk = Chunk::new((chunk_x, chunk_z));
+            generator.generate(&mut chunk, &ctx);
+
+            for (x, z) in [(0, 0), (7, 11), (15, 15)] {
+                let (_, voxel) = chunk.surface(x, z).unwrap();
+                assert_eq!(voxel.kind, grass);
+
+                let pixel = image.get_pixel(
+                    ((chunk_x + 2) * 16) as u32 + x as u32,
+                    ((chunk_z + 1) * 16) as u32 + z as u32,
+                );
+                let [r, g, b] =
+                    column_color(&chunk, x, z).map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+
+                assert_eq!(*pixel, Rgb([r, g, b]));
+                assert!(g > r && g > b, "Grass should be green, not {:?}", pixel);
+            }
+        }
+    }
+}
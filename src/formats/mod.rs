@@ -0,0 +1,40 @@
+// Imports and exports models in the formats of other tools.
+pub mod glb;
+pub mod map;
+pub mod nbt;
+pub mod obj;
+pub mod schem;
+pub mod selection;
+pub mod vox;
+
+use std::{io, path::Path};
+
+use crate::rendering::mesh::Mesh;
+
+/// The file formats that meshes can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    /// A Wavefront .obj file, with its materials in a .mtl file.
+    Obj,
+    /// A binary glTF file.
+    Glb,
+}
+
+impl MeshFormat {
+    /// Picks the format from the extension of a path.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("obj") => Some(Self::Obj),
+            Some("glb") => Some(Self::Glb),
+            _ => None,
+        }
+    }
+
+    /// Writes a mesh to a file in this format.
+    pub fn save(self, mesh: &Mesh, path: impl AsRef<Path>) -> io::Result<()> {
+        match self {
+            Self::Obj => obj::save_obj(mesh, path),
+            Self::Glb => glb::save_glb(mesh, path),
+        }
+    }
+}
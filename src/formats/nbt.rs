@@ -0,0 +1,257 @@
+#![allow(dead_code)]
+
+use std::{collections::BTreeMap, io};
+
+use crate::storage::chunk_format::{invalid_data, ByteReader};
+
+/// The named tags of a compound, sorted by name.
+pub type NbtCompound = BTreeMap<String, NbtTag>;
+
+/// The deepest lists and compounds can be nested, so that malformed files
+/// can't overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+/// A single value in Minecraft's Named Binary Tag format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtTag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<NbtTag>),
+    Compound(NbtCompound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl NbtTag {
+    /// Returns the ID written before the tag.
+    pub const fn id(&self) -> u8 {
+        match self {
+            NbtTag::Byte(_) => 1,
+            NbtTag::Short(_) => 2,
+            NbtTag::Int(_) => 3,
+            NbtTag::Long(_) => 4,
+            NbtTag::Float(_) => 5,
+            NbtTag::Double(_) => 6,
+            NbtTag::ByteArray(_) => 7,
+            NbtTag::String(_) => 8,
+            NbtTag::List(_) => 9,
+            NbtTag::Compound(_) => 10,
+            NbtTag::IntArray(_) => 11,
+            NbtTag::LongArray(_) => 12,
+        }
+    }
+
+    /// Returns the value of any integer tag.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            NbtTag::Byte(value) => Some(*value as i64),
+            NbtTag::Short(value) => Some(*value as i64),
+            NbtTag::Int(value) => Some(*value as i64),
+            NbtTag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if the tag is a compound.
+    pub fn as_compound(&self) -> Option<&NbtCompound> {
+        match self {
+            NbtTag::Compound(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if the tag is a byte array.
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            NbtTag::ByteArray(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if the tag is an int array.
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match self {
+            NbtTag::IntArray(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Writes the payload of the tag, without its ID or name.
+    fn write(&self, bytes: &mut Vec<u8>) {
+        match self {
+            NbtTag::Byte(value) => bytes.push(*value as u8),
+            NbtTag::Short(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            NbtTag::Int(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            NbtTag::Long(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            NbtTag::Float(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            NbtTag::Double(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            NbtTag::ByteArray(values) => {
+                bytes.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                bytes.extend(values.iter().map(|value| *value as u8));
+            }
+            NbtTag::String(value) => write_string(bytes, value),
+            NbtTag::List(values) => {
+                // Empty lists are written with the ID of the end tag
+                bytes.push(values.first().map_or(0, NbtTag::id));
+                bytes.extend_from_slice(&(values.len() as i32).to_be_bytes());
+
+                for value in values {
+                    value.write(bytes);
+                }
+            }
+            NbtTag::Compound(compound) => write_compound(bytes, compound),
+            NbtTag::IntArray(values) => {
+                bytes.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                for value in values {
+                    bytes.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            NbtTag::LongArray(values) => {
+                bytes.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                for value in values {
+                    bytes.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        }
+    }
+
+    /// Reads the payload of a tag with the given ID.
+    fn read(reader: &mut ByteReader, id: u8, depth: usize) -> io::Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("NBT is nested too deeply".to_string()));
+        }
+
+        let tag = match id {
+            1 => NbtTag::Byte(i8::from_be_bytes(reader.take()?)),
+            2 => NbtTag::Short(i16::from_be_bytes(reader.take()?)),
+            3 => NbtTag::Int(i32::from_be_bytes(reader.take()?)),
+            4 => NbtTag::Long(i64::from_be_bytes(reader.take()?)),
+            5 => NbtTag::Float(f32::from_be_bytes(reader.take()?)),
+            6 => NbtTag::Double(f64::from_be_bytes(reader.take()?)),
+            7 => {
+                let length = read_length(reader)?;
+                let values = reader.slice(length)?;
+
+                NbtTag::ByteArray(values.iter().map(|value| *value as i8).collect())
+            }
+            8 => NbtTag::String(read_string(reader)?),
+            9 => {
+                let id = reader.u8()?;
+                let length = read_length(reader)?;
+
+                let mut values = Vec::with_capacity(length.min(reader.remaining()));
+                for _ in 0..length {
+                    values.push(Self::read(reader, id, depth + 1)?);
+                }
+
+                NbtTag::List(values)
+            }
+            10 => NbtTag::Compound(read_compound(reader, depth + 1)?),
+            11 => {
+                let length = read_length(reader)?;
+                let values = reader.slice(length * 4)?;
+
+                NbtTag::IntArray(
+                    values
+                        .chunks_exact(4)
+                        .map(|value| i32::from_be_bytes(value.try_into().unwrap()))
+                        .collect(),
+                )
+            }
+            12 => {
+                let length = read_length(reader)?;
+                let values = reader.slice(length * 8)?;
+
+                NbtTag::LongArray(
+                    values
+                        .chunks_exact(8)
+                        .map(|value| i64::from_be_bytes(value.try_into().unwrap()))
+                        .collect(),
+                )
+            }
+            _ => return Err(invalid_data(format!("Unknown NBT tag ID {}", id))),
+        };
+
+        Ok(tag)
+    }
+}
+
+/// Encodes a named root compound. The result is usually gzip compressed.
+pub fn write_nbt(name: &str, root: &NbtCompound) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    bytes.push(10);
+    write_string(&mut bytes, name);
+    write_compound(&mut bytes, root);
+
+    bytes
+}
+
+/// Decodes a named root compound, returning its name and tags.
+pub fn read_nbt(bytes: &[u8]) -> io::Result<(String, NbtCompound)> {
+    let mut reader = ByteReader::new(bytes);
+
+    if reader.u8()? != 10 {
+        return Err(invalid_data(
+            "The root NBT tag must be a compound".to_string(),
+        ));
+    }
+
+    let name = read_string(&mut reader)?;
+    let root = read_compound(&mut reader, 0)?;
+
+    Ok((name, root))
+}
+
+/// Writes the tags of a compound, followed by an end tag.
+fn write_compound(bytes: &mut Vec<u8>, compound: &NbtCompound) {
+    for (name, tag) in compound {
+        bytes.push(tag.id());
+        write_string(bytes, name);
+        tag.write(bytes);
+    }
+
+    bytes.push(0);
+}
+
+/// Reads the tags of a compound, up to and including its end tag.
+fn read_compound(reader: &mut ByteReader, depth: usize) -> io::Result<NbtCompound> {
+    let mut compound = NbtCompound::new();
+
+    loop {
+        let id = reader.u8()?;
+        if id == 0 {
+            return Ok(compound);
+        }
+
+        let name = read_string(reader)?;
+        compound.insert(name, NbtTag::read(reader, id, depth)?);
+    }
+}
+
+/// Writes a string prefixed with its length in bytes.
+fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+/// Reads a string prefixed with its length in bytes.
+fn read_string(reader: &mut ByteReader) -> io::Result<String> {
+    let length = u16::from_be_bytes(reader.take()?) as usize;
+
+    String::from_utf8(reader.slice(length)?.to_vec())
+        .map_err(|_| invalid_data("NBT string is not valid UTF-8".to_string()))
+}
+
+/// Reads the length of an array or list, which can't be negative.
+fn read_length(reader: &mut ByteReader) -> io::Result<usize> {
+    let length = i32::from_be_bytes(reader.take()?);
+
+    usize::try_from(length).map_err(|_| invalid_data(format!("Negative NBT length {}", length)))
+}
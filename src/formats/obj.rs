@@ -0,0 +1,165 @@
+#![allow(dead_code)]
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    registry::{block_registry, MAX_LIGHT_LEVEL},
+    rendering::mesh::Mesh,
+    voxel::VoxelKind,
+};
+
+/// Writes a mesh to a Wavefront .obj file, and the materials of its block
+/// types to a .mtl file next to it.
+pub fn save_obj(mesh: &Mesh, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let mtl_path = path.with_extension("mtl");
+
+    let mut obj = BufWriter::new(File::create(path)?);
+    let material_library = mtl_path.file_name().and_then(|name| name.to_str());
+    write_obj(mesh, &mut obj, material_library)?;
+    obj.flush()?;
+
+    let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+    write_mtl(mesh, &mut mtl)?;
+    mtl.flush()
+}
+
+/// Writes the vertices, normals, texture coordinates and faces of a mesh in
+/// the .obj format.
+/// Faces are grouped by block type, each using the material of the same name.
+pub fn write_obj(
+    mesh: &Mesh,
+    writer: &mut impl Write,
+    material_library: Option<&str>,
+) -> io::Result<()> {
+    if let Some(material_library) = material_library {
+        writeln!(writer, "mtllib {}", material_library)?;
+    }
+
+    writeln!(writer, "o terrain")?;
+
+    for vertex in mesh.vertices.iter() {
+        let (x, y, z) = vertex.position;
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+
+    for vertex in mesh.vertices.iter() {
+        let (x, y, z) = vertex.normal();
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+
+    for vertex in mesh.vertices.iter() {
+        let (u, v) = vertex.uv();
+        writeln!(writer, "vt {} {}", u, v)?;
+    }
+
+    for (kind, indices) in mesh.indices_by_kind() {
+        writeln!(writer, "usemtl {}", material_name(kind))?;
+
+        // Indices in .obj files start at 1
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+            writeln!(writer, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a material for every block type in a mesh, in the .mtl format.
+pub fn write_mtl(mesh: &Mesh, writer: &mut impl Write) -> io::Result<()> {
+    for kind in mesh.indices_by_kind().keys() {
+        let (tint, emission) = match block_registry().get(*kind) {
+            Some(block) => (
+                block.tint,
+                block.light_emission as f32 / MAX_LIGHT_LEVEL as f32,
+            ),
+            None => ((1.0, 0.0, 1.0), 0.0),
+        };
+
+        writeln!(writer, "newmtl {}", material_name(*kind))?;
+        writeln!(writer, "Kd {} {} {}", tint.0, tint.1, tint.2)?;
+        writeln!(writer, "Ke {} {} {}", emission, emission, emission)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the name of the material used for a block type.
+pub fn material_name(kind: VoxelKind) -> String {
+    match block_registry().get(kind) {
+        Some(block) => block.name.clone(),
+        None => format!("block_{}", kind.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::Chunk,
+        rendering::mesh::{MeshBuilder, MeshingMode},
+        world::World,
+    };
+
+    /// Builds the mesh of two separate blocks of different types.
+    fn two_blocks() -> Mesh {
+        crate::init_test_globals();
+
+        let mut world = World::new();
+        world.insert(Chunk::new((0, 0)));
+        world.set_block(3, 10, 4, block_registry().kind("stone"));
+        world.set_block(6, 10, 4, block_registry().kind("dirt"));
+
+        MeshBuilder::with_mode(MeshingMode::Naive).build(&world)
+    }
+
+    #[test]
+    fn obj_files_have_a_vertex_normal_and_uv_per_vertex() {
+        let mesh = two_blocks();
+
+        let mut obj = Vec::new();
+        write_obj(&mesh, &mut obj, Some("blocks.mtl")).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+
+        let count = |prefix: &str| {
+            obj.lines()
+                .filter(|line| line.split(' ').next() == Some(prefix))
+                .count()
+        };
+
+        // Six quads for each block
+        assert_eq!(count("v"), 48);
+        assert_eq!(count("vn"), 48);
+        assert_eq!(count("vt"), 48);
+        assert_eq!(count("f"), 24);
+        assert_eq!(count("usemtl"), 2);
+        assert_eq!(obj.lines().next(), Some("mtllib blocks.mtl"));
+
+        // Every corner of a face uses the same vertex, normal and UV
+        for face in obj.lines().filter(|line| line.starts_with("f ")) {
+            for corner in face.split(' ').skip(1) {
+                let indices = corner
+                    .split('/')
+                    .map(|index| index.parse::<usize>().unwrap())
+                    .collect::<Vec<_>>();
+
+                assert_eq!(indices.len(), 3);
+                assert!(indices.iter().all(|index| *index == indices[0]));
+                assert!((1..=48).contains(&indices[0]));
+            }
+        }
+
+        let mut mtl = Vec::new();
+        write_mtl(&mesh, &mut mtl).unwrap();
+        let mtl = String::from_utf8(mtl).unwrap();
+
+        assert!(mtl.contains("newmtl stone\n"));
+        assert!(mtl.contains("newmtl dirt\n"));
+    }
+}
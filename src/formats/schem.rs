@@ -0,0 +1,430 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{
+    block_state::{BlockProperty, BlockState},
+    formats::{
+        nbt::{read_nbt, write_nbt, NbtCompound, NbtTag},
+        selection::Selection,
+    },
+    registry::block_registry,
+    storage::chunk_format::invalid_data,
+    voxel::{Voxel, VoxelKind},
+    world::World,
+};
+
+/// The version of the Sponge schematic format that is written.
+pub const SCHEM_VERSION: i32 = 2;
+
+/// The Minecraft data version written to exported schematics (1.16.5).
+const DATA_VERSION: i32 = 2586;
+
+/// The namespace added to block names when exporting. The engine's blocks
+/// don't match Minecraft's, so they get a namespace of their own.
+const NAMESPACE: &str = "voxelengine";
+
+/// A box of blocks stored in the Sponge schematic format (`.schem`).
+///
+/// The file is a gzip compressed NBT compound holding the size of the box, a
+/// palette of block names (such as `voxelengine:log[facing=up]`), and the
+/// palette index of every block as a varint, ordered by x, then z, then y.
+///
+/// Block names are the engine's own, so the export is only lossless when it
+/// is loaded back into the engine. Air is written as `minecraft:air`, so
+/// other tools still see the empty space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    /// The number of blocks along the x, y and z axes.
+    pub size: (u16, u16, u16),
+
+    /// The world position the schematic was copied from.
+    pub offset: (i32, i32, i32),
+
+    /// Every block in the box, indexed by `index`.
+    blocks: Vec<Voxel>,
+}
+
+impl Schematic {
+    /// Copies the blocks inside a selection, including air.
+    /// Blocks in chunks that haven't been built are stored as air.
+    pub fn from_world(world: &World, selection: Selection) -> Result<Self, String> {
+        let (width, height, length) = selection.size();
+
+        let size = match (
+            u16::try_from(width),
+            u16::try_from(height),
+            u16::try_from(length),
+        ) {
+            (Ok(width), Ok(height), Ok(length)) => (width, height, length),
+            _ => return Err("Selections can be at most 65535 blocks along each axis".to_string()),
+        };
+
+        let mut schematic = Self {
+            size,
+            offset: selection.min,
+            blocks: vec![
+                Voxel::new(VoxelKind::AIR);
+                width as usize * height as usize * length as usize
+            ],
+        };
+
+        let (min_x, min_y, min_z) = selection.min;
+
+        for y in 0..size.1 {
+            for z in 0..size.2 {
+                for x in 0..size.0 {
+                    let voxel = world
+                        .get_block(min_x + x as i32, min_y + y as i32, min_z + z as i32)
+                        .unwrap_or(Voxel::new(VoxelKind::AIR));
+
+                    let index = schematic.index(x, y, z);
+                    schematic.blocks[index] = voxel;
+                }
+            }
+        }
+
+        Ok(schematic)
+    }
+
+    /// Returns the index of a block in `blocks`.
+    fn index(&self, x: u16, y: u16, z: u16) -> usize {
+        let (width, _, length) = self.size;
+
+        x as usize + z as usize * width as usize + y as usize * width as usize * length as usize
+    }
+
+    /// Gets the block at a position relative to the schematic's corner.
+    pub fn get(&self, x: u16, y: u16, z: u16) -> Option<Voxel> {
+        let (width, height, length) = self.size;
+
+        if x >= width || y >= height || z >= length {
+            return None;
+        }
+
+        Some(self.blocks[self.index(x, y, z)])
+    }
+
+    /// Pastes every block of the schematic (including air) into the world,
+    /// with its minimum corner at `origin`. Blocks in chunks that haven't been
+    /// built are skipped. Returns the number of blocks placed.
+    pub fn paste(&self, world: &mut World, origin: (i32, i32, i32)) -> usize {
+        let mut placed = 0;
+
+        for y in 0..self.size.1 {
+            for z in 0..self.size.2 {
+                for x in 0..self.size.0 {
+                    let voxel = self.blocks[self.index(x, y, z)];
+
+                    if world.set_block(
+                        origin.0 + x as i32,
+                        origin.1 + y as i32,
+                        origin.2 + z as i32,
+                        voxel,
+                    ) {
+                        placed += 1;
+                    }
+                }
+            }
+        }
+
+        placed
+    }
+
+    /// Converts the schematic into its root NBT compound.
+    pub fn to_nbt(&self) -> NbtCompound {
+        let mut palette = HashMap::<Voxel, i32>::new();
+        let mut block_data = Vec::with_capacity(self.blocks.len());
+
+        for voxel in self.blocks.iter() {
+            let next = palette.len() as i32;
+            let index = *palette.entry(*voxel).or_insert(next);
+
+            write_varint(&mut block_data, index as u32);
+        }
+
+        let palette_nbt = palette
+            .iter()
+            .map(|(voxel, index)| (block_name(*voxel), NbtTag::Int(*index)))
+            .collect::<NbtCompound>();
+
+        let (width, height, length) = self.size;
+        let (x, y, z) = self.offset;
+
+        let mut root = NbtCompound::new();
+        root.insert("Version".to_string(), NbtTag::Int(SCHEM_VERSION));
+        root.insert("DataVersion".to_string(), NbtTag::Int(DATA_VERSION));
+        root.insert("Width".to_string(), NbtTag::Short(width as i16));
+        root.insert("Height".to_string(), NbtTag::Short(height as i16));
+        root.insert("Length".to_string(), NbtTag::Short(length as i16));
+        root.insert("Offset".to_string(), NbtTag::IntArray(vec![x, y, z]));
+        root.insert("PaletteMax".to_string(), NbtTag::Int(palette.len() as i32));
+        root.insert("Palette".to_string(), NbtTag::Compound(palette_nbt));
+        root.insert(
+            "BlockData".to_string(),
+            NbtTag::ByteArray(block_data.into_iter().map(|b| b as i8).collect()),
+        );
+
+        root
+    }
+
+    /// Reads a schematic from its root NBT compound.
+    /// Blocks that aren't in the registry are replaced with air.
+    pub fn from_nbt(root: &NbtCompound) -> Result<Self, String> {
+        let integer = |name: &str| {
+            root.get(name)
+                .and_then(NbtTag::as_integer)
+                .ok_or_else(|| format!("Missing '{}'", name))
+        };
+
+        let version = integer("Version")?;
+        if !(1..=SCHEM_VERSION as i64).contains(&version) {
+            return Err(format!("Unsupported schematic version {}", version));
+        }
+
+        // Sizes are stored as signed shorts, but are never negative
+        let dimension = |name: &str| integer(name).map(|value| value as i16 as u16);
+        let size = (
+            dimension("Width")?,
+            dimension("Height")?,
+            dimension("Length")?,
+        );
+
+        let offset = match root.get("Offset").and_then(NbtTag::as_int_array) {
+            Some([x, y, z]) => (*x, *y, *z),
+            _ => (0, 0, 0),
+        };
+
+        let mut palette = HashMap::new();
+        let mut unknown = BTreeSet::new();
+
+        for (name, index) in root
+            .get("Palette")
+            .and_then(NbtTag::as_compound)
+            .ok_or("Missing 'Palette'")?
+        {
+            let index = index
+                .as_integer()
+                .ok_or_else(|| format!("Invalid palette index for '{}'", name))?;
+
+            let voxel = parse_block_name(name).unwrap_or_else(|| {
+                unknown.insert(name.as_str());
+                Voxel::new(VoxelKind::AIR)
+            });
+
+            palette.insert(index as u32, voxel);
+        }
+
+        if !unknown.is_empty() {
+            log::warn!("Replaced unknown blocks with air: {:?}", unknown);
+        }
+
+        let block_data = root
+            .get("BlockData")
+            .and_then(NbtTag::as_byte_array)
+            .ok_or("Missing 'BlockData'")?;
+
+        let volume = size.0 as usize * size.1 as usize * size.2 as usize;
+        let mut blocks = Vec::with_capacity(volume);
+        let mut bytes = block_data.iter().map(|byte| *byte as u8);
+
+        while blocks.len() < volume {
+            let index = read_varint(&mut bytes).ok_or("Truncated 'BlockData'")?;
+            let voxel = palette
+                .get(&index)
+                .ok_or_else(|| format!("Block data refers to missing palette index {}", index))?;
+
+            blocks.push(*voxel);
+        }
+
+        Ok(Self {
+            size,
+            offset,
+            blocks,
+        })
+    }
+
+    /// Reads a gzip compressed schematic file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        GzDecoder::new(std::fs::File::open(path)?).read_to_end(&mut bytes)?;
+
+        let (_, root) = read_nbt(&bytes)?;
+
+        Self::from_nbt(&root).map_err(invalid_data)
+    }
+
+    /// Writes the schematic to a gzip compressed file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut encoder = GzEncoder::new(std::fs::File::create(path)?, Compression::default());
+        encoder.write_all(&write_nbt("Schematic", &self.to_nbt()))?;
+        encoder.finish()?.sync_all()
+    }
+}
+
+/// Returns the namespaced name of a block with its properties,
+/// such as `voxelengine:log[facing=up]`.
+fn block_name(voxel: Voxel) -> String {
+    let block = match block_registry().get(voxel.kind) {
+        Some(block) if voxel.kind != VoxelKind::AIR => block,
+        _ => return "minecraft:air".to_string(),
+    };
+
+    let properties = block
+        .properties
+        .iter()
+        .map(|property| format!("{}={}", property.name(), voxel.state.value_name(*property)))
+        .collect::<Vec<_>>();
+
+    if properties.is_empty() {
+        format!("{}:{}", NAMESPACE, block.name)
+    } else {
+        format!("{}:{}[{}]", NAMESPACE, block.name, properties.join(","))
+    }
+}
+
+/// Parses a block name written by `block_name`. Any namespace is ignored, as are
+/// properties the block doesn't have. Returns `None` if the block is unknown.
+fn parse_block_name(name: &str) -> Option<Voxel> {
+    let (name, properties) = match name.split_once('[') {
+        Some((name, properties)) => (name, properties.strip_suffix(']')?),
+        None => (name, ""),
+    };
+
+    let name = name.rsplit(':').next().unwrap_or(name);
+    let block = block_registry().get(block_registry().by_name(name)?)?;
+
+    let mut state = BlockState::default();
+
+    for (property, value) in properties
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+    {
+        if let Some(property) = BlockProperty::from_name(property) {
+            state = state.with_value_name(property, value).unwrap_or(state);
+        }
+    }
+
+    Some(block.voxel(state))
+}
+
+/// Writes an unsigned LEB128 varint.
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+/// Reads an unsigned LEB128 varint.
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<u32> {
+    let mut value = 0;
+
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7f) as u32) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_state::{Facing, Half},
+        chunk::Chunk,
+    };
+
+    #[test]
+    fn schematics_round_trip_through_files() {
+        crate::init_test_globals();
+        let registry = block_registry();
+
+        // Blocks with and without states, so that both are written to the palette
+        let stairs = registry.kind("stone_stairs");
+        let slab = registry.kind("stone_slab");
+        let voxels = [
+            Voxel::new(registry.kind("grass")),
+            Voxel::new(registry.kind("stone")),
+            Voxel::with_state(stairs, BlockState::default().with_facing(Facing::East)),
+            Voxel::with_state(stairs, BlockState::default().with_facing(Facing::South)),
+            Voxel::with_state(slab, BlockState::default().with_half(Half::Upper)),
+        ];
+
+        let mut world = World::new();
+        world.insert(Chunk::new((-1, 0)));
+        world.insert(Chunk::new((0, 0)));
+
+        let selection = Selection::new((-4, 10, 2), (5, 14, 8));
+
+        for x in -4..=5i32 {
+            for y in 10..=14 {
+                for z in 2..=8 {
+                    let choice = (x * 7 + y * 3 + z * 5).rem_euclid(7) as usize;
+                    if let Some(voxel) = voxels.get(choice) {
+                        world.set_block(x, y, z, *voxel);
+                    }
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("schem_test_{}.schem", std::process::id()));
+
+        let schematic = Schematic::from_world(&world, selection).unwrap();
+        schematic.save(&path).unwrap();
+
+        let loaded = Schematic::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, schematic);
+
+        let mut pasted = World::new();
+        pasted.insert(Chunk::new((-1, 0)));
+        pasted.insert(Chunk::new((0, 0)));
+        assert_eq!(loaded.paste(&mut pasted, selection.min), 10 * 5 * 7);
+
+        for x in -4..=5 {
+            for y in 10..=14 {
+                for z in 2..=8 {
+                    assert_eq!(
+                        pasted.get_block(x, y, z),
+                        world.get_block(x, y, z),
+                        "{:?}",
+                        (x, y, z)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_are_named_in_the_engine_namespace() {
+        crate::init_test_globals();
+        let registry = block_registry();
+
+        let stairs = Voxel::with_state(
+            registry.kind("stone_stairs"),
+            BlockState::default().with_facing(Facing::East),
+        );
+
+        assert_eq!(block_name(Voxel::new(VoxelKind::AIR)), "minecraft:air");
+        assert_eq!(
+            block_name(Voxel::new(registry.kind("stone"))),
+            "voxelengine:stone"
+        );
+        assert_eq!(parse_block_name(&block_name(stairs)), Some(stairs));
+    }
+}
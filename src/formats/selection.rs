@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+
+/// An axis-aligned box of blocks in the world. Both corners are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    /// The corner with the lowest coordinates.
+    pub min: (i32, i32, i32),
+    /// The corner with the highest coordinates.
+    pub max: (i32, i32, i32),
+}
+
+impl Selection {
+    /// Creates a selection between two opposite corners, in any order.
+    pub fn new(a: (i32, i32, i32), b: (i32, i32, i32)) -> Self {
+        Self {
+            min: (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            max: (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        }
+    }
+
+    /// Returns the number of blocks along the x, y and z axes.
+    pub fn size(&self) -> (u32, u32, u32) {
+        (
+            self.max.0.abs_diff(self.min.0) + 1,
+            self.max.1.abs_diff(self.min.1) + 1,
+            self.max.2.abs_diff(self.min.2) + 1,
+        )
+    }
+
+    /// Returns true if the position lies inside of the selection.
+    pub fn contains(&self, (x, y, z): (i32, i32, i32)) -> bool {
+        (self.min.0..=self.max.0).contains(&x)
+            && (self.min.1..=self.max.1).contains(&y)
+            && (self.min.2..=self.max.2).contains(&z)
+    }
+}
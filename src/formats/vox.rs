@@ -0,0 +1,479 @@
+#![allow(dead_code)]
+
+use std::{io, path::Path};
+
+use crate::{
+    config::{Config, ConfigTable},
+    formats::selection::Selection,
+    registry::block_registry,
+    storage::chunk_format::{invalid_data, ByteReader},
+    voxel::VoxelKind,
+    world::World,
+};
+
+/// The magic bytes at the start of every .vox file.
+const VOX_MAGIC: &[u8; 4] = b"VOX ";
+
+/// The version written to exported files.
+const VOX_VERSION: u32 = 150;
+
+/// The largest size of a model along each axis.
+pub const MAX_VOX_SIZE: u32 = 256;
+
+/// The default location of the palette mapping.
+pub const VOX_MAPPING_PATH: &str = "./assets/vox_palette.toml";
+
+/// A single voxel of a model, with its colour as an index into the palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxVoxel {
+    pub x: u8,
+    pub y: u8,
+    pub z: u8,
+    pub color_index: u8,
+}
+
+/// A single model in a .vox file.
+#[derive(Debug, Clone, Default)]
+pub struct VoxModel {
+    /// The size of the model along MagicaVoxel's x, y and z axes (z is up).
+    pub size: (u32, u32, u32),
+
+    /// The voxels that aren't empty.
+    pub voxels: Vec<VoxVoxel>,
+}
+
+/// The contents of a MagicaVoxel .vox file.
+///
+/// The file starts with the magic bytes and a version, followed by a `MAIN`
+/// chunk. Its children are a `SIZE` and `XYZI` chunk for every model, and an
+/// optional `RGBA` palette. Any other chunks (such as the scene graph) are skipped.
+#[derive(Debug, Clone)]
+pub struct VoxFile {
+    /// The models in the file, in the order they appear.
+    pub models: Vec<VoxModel>,
+
+    /// The RGBA colour of each palette index. Index 0 is always empty.
+    /// Defaults to white when the file has no palette.
+    pub palette: [[u8; 4]; 256],
+}
+
+impl VoxFile {
+    /// Reads a .vox file from disk.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        Self::parse(&std::fs::read(path)?)
+            .map_err(|err| invalid_data(format!("Failed to read '{}': {}", path.display(), err)))
+    }
+
+    /// Parses the contents of a .vox file.
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = ByteReader::new(bytes);
+
+        if &reader.take::<4>()? != VOX_MAGIC {
+            return Err(invalid_data("Not a MagicaVoxel file".to_string()));
+        }
+
+        // The version has changed without breaking the chunks we read
+        let _version = reader.u32()?;
+
+        let (id, content, children) = read_chunk(&mut reader)?;
+        if &id != b"MAIN" || !content.is_empty() {
+            return Err(invalid_data("Expected a MAIN chunk".to_string()));
+        }
+
+        let mut file = Self {
+            models: Vec::new(),
+            palette: [[255; 4]; 256],
+        };
+
+        let mut size = None;
+        let mut reader = ByteReader::new(children);
+
+        while reader.remaining() > 0 {
+            let (id, content, _) = read_chunk(&mut reader)?;
+            let mut content = ByteReader::new(content);
+
+            match &id {
+                b"SIZE" => {
+                    size = Some((content.u32()?, content.u32()?, content.u32()?));
+                }
+                b"XYZI" => {
+                    let size = size
+                        .take()
+                        .ok_or_else(|| invalid_data("XYZI chunk without a SIZE".to_string()))?;
+
+                    let count = content.u32()? as usize;
+                    let mut voxels = Vec::with_capacity(count.min(content.remaining() / 4));
+
+                    for _ in 0..count {
+                        let [x, y, z, color_index] = content.take()?;
+                        voxels.push(VoxVoxel {
+                            x,
+                            y,
+                            z,
+                            color_index,
+                        });
+                    }
+
+                    file.models.push(VoxModel { size, voxels });
+                }
+                b"RGBA" => {
+                    // The colour of index i is stored in entry i - 1
+                    for index in 1..256 {
+                        file.palette[index] = content.take()?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if file.models.is_empty() {
+            return Err(invalid_data(
+                "The file doesn't contain any models".to_string(),
+            ));
+        }
+
+        Ok(file)
+    }
+
+    /// Encodes the file, with a `SIZE` and `XYZI` chunk for every model.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut children = Vec::new();
+
+        for model in self.models.iter() {
+            let (x, y, z) = model.size;
+            let mut size = Vec::with_capacity(12);
+            for value in [x, y, z] {
+                size.extend_from_slice(&value.to_le_bytes());
+            }
+            write_chunk(&mut children, b"SIZE", &size, &[]);
+
+            let mut voxels = Vec::with_capacity(4 + model.voxels.len() * 4);
+            voxels.extend_from_slice(&(model.voxels.len() as u32).to_le_bytes());
+            for voxel in model.voxels.iter() {
+                voxels.extend_from_slice(&[voxel.x, voxel.y, voxel.z, voxel.color_index]);
+            }
+            write_chunk(&mut children, b"XYZI", &voxels, &[]);
+        }
+
+        // The colour of index i is stored in entry i - 1, and the last of the
+        // 256 entries is unused
+        let mut palette = self.palette[1..].concat();
+        palette.extend_from_slice(&[0; 4]);
+        write_chunk(&mut children, b"RGBA", &palette, &[]);
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(VOX_MAGIC);
+        bytes.extend_from_slice(&VOX_VERSION.to_le_bytes());
+        write_chunk(&mut bytes, b"MAIN", &[], &children);
+
+        bytes
+    }
+
+    /// Writes the file to disk.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+/// Writes the header and content of a chunk.
+fn write_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+    bytes.extend_from_slice(id);
+    bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(content);
+    bytes.extend_from_slice(children);
+}
+
+/// Reads the header and content of a chunk, returning its ID, content and children.
+fn read_chunk<'a>(reader: &mut ByteReader<'a>) -> io::Result<([u8; 4], &'a [u8], &'a [u8])> {
+    let id = reader.take::<4>()?;
+    let content_size = reader.u32()? as usize;
+    let children_size = reader.u32()? as usize;
+
+    Ok((
+        id,
+        reader.slice(content_size)?,
+        reader.slice(children_size)?,
+    ))
+}
+
+/// Maps the palette indices of .vox models to block types.
+#[derive(Debug, Clone)]
+pub struct VoxMapping {
+    /// The block placed for each palette index. Air is skipped when placing.
+    blocks: [VoxelKind; 256],
+}
+
+impl VoxMapping {
+    /// Creates a mapping that places the same block for every index.
+    pub fn uniform(kind: VoxelKind) -> Self {
+        Self {
+            blocks: [kind; 256],
+        }
+    }
+
+    /// Loads the mapping from the given file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::from_config(&Config::load(path)?)
+    }
+
+    /// Creates a mapping from a config, with a `default` block name and a
+    /// `[palette]` table of `index = "block"` pairs.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let registry = block_registry();
+        let block = |name: &str| {
+            registry
+                .by_name(name)
+                .ok_or_else(|| format!("Unknown block '{}'", name))
+        };
+
+        let default = config.root.get_str("default").unwrap_or("stone");
+        let mut mapping = Self::uniform(block(default)?);
+
+        for (index, name) in config
+            .tables
+            .get("palette")
+            .into_iter()
+            .flat_map(ConfigTable::iter)
+        {
+            let index = index
+                .parse::<u8>()
+                .ok()
+                .filter(|index| *index != 0)
+                .ok_or_else(|| format!("Invalid palette index '{}'", index))?;
+            let name = name
+                .as_str()
+                .ok_or_else(|| format!("Palette index {} must map to a block name", index))?;
+
+            mapping.set(index, block(name)?);
+        }
+
+        Ok(mapping)
+    }
+
+    /// Gets the block placed for a palette index.
+    pub fn get(&self, color_index: u8) -> VoxelKind {
+        self.blocks[color_index as usize]
+    }
+
+    /// Sets the block placed for a palette index.
+    pub fn set(&mut self, color_index: u8, kind: VoxelKind) {
+        self.blocks[color_index as usize] = kind;
+    }
+
+    /// Returns the lowest palette index that places the given block.
+    pub fn index_of(&self, kind: VoxelKind) -> Option<u8> {
+        (1..=255).find(|index| self.get(*index) == kind)
+    }
+
+    /// Returns the colour of each palette index, taken from the tint of its block.
+    pub fn palette(&self) -> [[u8; 4]; 256] {
+        let registry = block_registry();
+
+        std::array::from_fn(|index| {
+            let (r, g, b) = registry
+                .get(self.blocks[index])
+                .map_or((1.0, 1.0, 1.0), |block| block.tint);
+
+            let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            [channel(r), channel(g), channel(b), 255]
+        })
+    }
+}
+
+impl VoxModel {
+    /// Creates a model from the blocks inside a selection. The axes are rotated
+    /// the opposite way to `blocks`, so that pasting the model at the selection's
+    /// minimum corner restores the same blocks. Block states aren't stored.
+    /// Fails if the selection is too large, or has a block the mapping doesn't place.
+    pub fn from_world(
+        world: &World,
+        selection: Selection,
+        mapping: &VoxMapping,
+    ) -> Result<Self, String> {
+        let (width, height, depth) = selection.size();
+
+        if width.max(height).max(depth) > MAX_VOX_SIZE {
+            return Err(format!(
+                "Selections can be at most {} blocks along each axis",
+                MAX_VOX_SIZE
+            ));
+        }
+
+        let (min_x, min_y, min_z) = selection.min;
+        let mut voxels = Vec::new();
+
+        for x in 0..width {
+            for y in 0..height {
+                for z in 0..depth {
+                    let kind = world
+                        .get_block(min_x + x as i32, min_y + y as i32, min_z + z as i32)
+                        .map_or(VoxelKind::AIR, |voxel| voxel.kind);
+
+                    if kind == VoxelKind::AIR {
+                        continue;
+                    }
+
+                    let color_index = mapping.index_of(kind).ok_or_else(|| {
+                        let name = block_registry().get(kind).map_or("unknown", |b| &b.name);
+                        format!("Block '{}' has no palette index in the mapping", name)
+                    })?;
+
+                    voxels.push(VoxVoxel {
+                        x: x as u8,
+                        y: (depth - 1 - z) as u8,
+                        z: y as u8,
+                        color_index,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            size: (width, depth, height),
+            voxels,
+        })
+    }
+
+    /// Returns the world position and block of every voxel, with the model's
+    /// minimum corner at `origin`. MagicaVoxel's z axis points up, so the model
+    /// is rotated to have its z axis along the world's y axis, and its y axis
+    /// along the world's -z axis.
+    pub fn blocks(
+        &self,
+        origin: (i32, i32, i32),
+        mapping: &VoxMapping,
+    ) -> impl Iterator<Item = ((i32, i32, i32), VoxelKind)> + '_ {
+        let mapping = mapping.clone();
+        let depth = self.size.1 as i32;
+
+        self.voxels.iter().filter_map(move |voxel| {
+            let kind = mapping.get(voxel.color_index);
+            if kind == VoxelKind::AIR {
+                return None;
+            }
+
+            let position = (
+                origin.0 + voxel.x as i32,
+                origin.1 + voxel.z as i32,
+                origin.2 + depth - 1 - voxel.y as i32,
+            );
+
+            Some((position, kind))
+        })
+    }
+
+    /// Pastes the model into the world with its minimum corner at `origin`.
+    /// Voxels in chunks that haven't been built are skipped.
+    /// Returns the number of blocks placed.
+    pub fn paste(&self, world: &mut World, origin: (i32, i32, i32), mapping: &VoxMapping) -> usize {
+        self.blocks(origin, mapping)
+            .filter(|&((x, y, z), kind)| world.set_block(x, y, z, kind))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::Chunk, voxel::Voxel};
+
+    #[test]
+    fn palettes_have_256_entries() {
+        let file = VoxFile {
+            models: vec![VoxModel {
+                size: (1, 1, 1),
+                voxels: Vec::new(),
+            }],
+            palette: [[10, 20, 30, 255]; 256],
+        };
+
+        let bytes = file.to_bytes();
+        let start = bytes
+            .windows(4)
+            .position(|id| id == b"RGBA")
+            .expect("The file has a palette");
+        let mut reader = ByteReader::new(&bytes[start..]);
+        let (_, content, children) = read_chunk(&mut reader).unwrap();
+
+        assert_eq!(content.len(), 256 * 4);
+        assert!(children.is_empty());
+        assert_eq!(content[..4], [10, 20, 30, 255]);
+        assert_eq!(content[255 * 4..], [0; 4]);
+
+        assert_eq!(
+            VoxFile::parse(&bytes).unwrap().palette[1..],
+            file.palette[1..]
+        );
+    }
+
+    #[test]
+    fn models_round_trip_through_files() {
+        crate::init_test_globals();
+        let registry = block_registry();
+
+        let mut mapping = VoxMapping::uniform(VoxelKind::AIR);
+        let kinds = ["grass", "dirt", "stone", "log"].map(|name| registry.kind(name));
+        for (index, kind) in kinds.iter().enumerate() {
+            mapping.set(index as u8 + 1, *kind);
+        }
+
+        // A selection spanning two chunks, with a mix of blocks and air
+        let mut world = World::new();
+        world.insert(Chunk::new((-1, 0)));
+        world.insert(Chunk::new((0, 0)));
+
+        let selection = Selection::new((-4, 10, 2), (5, 14, 8));
+
+        for x in -4..=5i32 {
+            for y in 10..=14 {
+                for z in 2..=8 {
+                    let choice = (x * 7 + y * 3 + z * 5).rem_euclid(5) as usize;
+                    if let Some(kind) = kinds.get(choice) {
+                        world.set_block(x, y, z, *kind);
+                    }
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("vox_test_{}.vox", std::process::id()));
+
+        let file = VoxFile {
+            models: vec![VoxModel::from_world(&world, selection, &mapping).unwrap()],
+            palette: mapping.palette(),
+        };
+        file.save(&path).unwrap();
+
+        let loaded = VoxFile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.palette, file.palette);
+        assert_eq!(loaded.models[0].size, (10, 7, 5));
+
+        let mut pasted = World::new();
+        pasted.insert(Chunk::new((-1, 0)));
+        pasted.insert(Chunk::new((0, 0)));
+        loaded.models[0].paste(&mut pasted, selection.min, &mapping);
+
+        for x in -4..=5 {
+            for y in 10..=14 {
+                for z in 2..=8 {
+                    assert_eq!(
+                        pasted.get_block(x, y, z),
+                        world.get_block(x, y, z),
+                        "{:?}",
+                        (x, y, z)
+                    );
+                }
+            }
+        }
+
+        // Blocks outside of the selection aren't touched
+        assert_eq!(
+            pasted.get_block(-5, 10, 2),
+            Some(Voxel::new(VoxelKind::AIR))
+        );
+    }
+}
@@ -1,27 +1,66 @@
+mod block_collections;
+mod block_state;
 mod buffers;
 mod chunk;
+mod cli;
+mod config;
+mod formats;
 mod input;
+mod registry;
 mod rendering;
+mod storage;
 mod systems;
 mod timer;
 mod utils;
 mod voxel;
+mod world;
 
-use std::sync::OnceLock;
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::OnceLock,
+};
 
 use chunk::Chunk;
+use world::World;
 
 use glfw::{Action, Context, Key, MouseButton, WindowEvent};
 use log::info;
 use nalgebra_glm as glm;
 
 use owo_colors::OwoColorize;
-use rendering::{camera::Camera, shader::shader_program::ShaderProgram};
+use registry::{block_registry, BlockRegistry, BLOCK_REGISTRY_PATH};
+use rendering::{
+    block_textures::{BlockTextures, BLOCK_TEXTURE_DIRECTORY},
+    camera::Camera,
+    shader::shader_program::ShaderProgram,
+};
 
 use crate::{
+    cli::{CommandLine, USAGE},
+    config::ConfigTable,
+    formats::{
+        map::save_map,
+        schem::Schematic,
+        selection::Selection,
+        vox::{VoxFile, VoxMapping, VoxModel, VOX_MAPPING_PATH},
+        MeshFormat,
+    },
     input::InputManager,
-    rendering::{camera::CAMERA_SPEED, mesh::MeshBuilder},
-    systems::{chunk_builder::ChunkGenStrategy, chunk_manager::ChunkManager},
+    rendering::{
+        camera::CAMERA_SPEED,
+        mesh::{MeshBuilder, MeshingMode},
+    },
+    storage::{
+        region::RegionStorage,
+        world_meta::{WorldDirectory, WorldMetadata},
+    },
+    systems::{
+        chunk_manager::ChunkManager,
+        generators::{
+            decorations::place_in_chunk, GenContext, GeneratorRegistry, TerrainGenerator,
+        },
+    },
+    utils::world_to_chunk_position,
 };
 
 const WIDTH: u32 = 1200;
@@ -29,8 +68,26 @@ const HEIGHT: u32 = 1200;
 
 const ASPECT_RATIO: f32 = WIDTH as f32 / HEIGHT as f32;
 
-pub static NOISE_SEED: OnceLock<u32> = OnceLock::new();
-pub static NOISE: OnceLock<noise::Perlin> = OnceLock::new();
+/// The world that is opened when no directory is given on the command line.
+const DEFAULT_WORLD_DIRECTORY: &str = "./world";
+
+/// The terrain generator of new worlds, unless another is chosen on the command line.
+const DEFAULT_GENERATOR: &str = "biomes";
+
+/// How many chunks away from the origin mesh exports reach.
+const MESH_EXPORT_RADIUS: i32 = 3;
+
+pub static BLOCK_REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+pub static GENERATOR_REGISTRY: OnceLock<GeneratorRegistry> = OnceLock::new();
+pub static BLOCK_TEXTURES: OnceLock<BlockTextures> = OnceLock::new();
+
+/// Loads the globals that `main` sets up, for tests that need them.
+#[cfg(test)]
+pub fn init_test_globals() {
+    let registry = BLOCK_REGISTRY.get_or_init(|| BlockRegistry::load(BLOCK_REGISTRY_PATH));
+    BLOCK_TEXTURES.get_or_init(|| BlockTextures::load(BLOCK_TEXTURE_DIRECTORY, registry).unwrap());
+    GENERATOR_REGISTRY.get_or_init(GeneratorRegistry::with_defaults);
+}
 
 fn main() {
     // Initialize the logger
@@ -40,15 +97,112 @@ fn main() {
         .format_module_path(false)
         .init();
 
-    // Initialize the noise seed
-    NOISE_SEED.get_or_init(rand::random::<u32>);
-    NOISE.get_or_init(|| noise::Perlin::new(*NOISE_SEED.get().unwrap()));
+    // Load the block definitions
+    let registry = BLOCK_REGISTRY.get_or_init(|| BlockRegistry::load(BLOCK_REGISTRY_PATH));
+
+    log::info!(
+        "Loaded {} block types",
+        registry.iter().count().cyan().bold()
+    );
+
+    // Load the images of the block faces
+    let textures = BLOCK_TEXTURES.get_or_init(|| {
+        BlockTextures::load(BLOCK_TEXTURE_DIRECTORY, registry)
+            .unwrap_or_else(|err| panic!("Failed to load block textures: {}", err))
+    });
+
+    log::info!(
+        "Loaded {} block textures",
+        (textures.layer_count() - 1).cyan().bold()
+    );
+
+    // Register the terrain generators
+    let generators = GENERATOR_REGISTRY.get_or_init(GeneratorRegistry::with_defaults);
+
+    log::info!(
+        "Registered {} terrain generators",
+        generators.names().count().cyan().bold()
+    );
+
+    // Parse the command line
+    let command_line = CommandLine::parse(std::env::args().skip(1), DEFAULT_WORLD_DIRECTORY)
+        .unwrap_or_else(|err| {
+            eprintln!("{}\n{}", err, USAGE);
+            std::process::exit(2);
+        });
+    let world_path = &command_line.world_path;
+
+    // The terrain chosen on the command line, used by exports and new worlds
+    let chosen_terrain = command_line.chooses_terrain().then(|| {
+        let seed = command_line.seed.unwrap_or_else(rand::random);
+        let name = command_line
+            .generator
+            .as_deref()
+            .unwrap_or(DEFAULT_GENERATOR);
+
+        let generator = generators
+            .create(name, seed, &command_line.generator_params)
+            .unwrap_or_else(|err| panic!("Failed to create terrain generator: {}", err));
+
+        (seed, generator)
+    });
+
+    // Exports of the chosen terrain don't need a world, so nothing is written to disk
+    if let Some((seed, generator)) = &chosen_terrain {
+        if command_line.exports() {
+            log::info!(
+                "Using seed {} with the '{}' generator",
+                seed.cyan().bold(),
+                generator.name().cyan().bold()
+            );
+
+            run_exports(&command_line, generator.as_ref(), None);
+            return;
+        }
+    }
+
+    // Open the world, or create a new one with the chosen terrain or a random seed
+    let mut created = false;
+
+    let mut world_directory = WorldDirectory::open_or_create(world_path, || {
+        created = true;
+
+        let (seed, generator) = chosen_terrain.clone().unwrap_or_else(|| {
+            let seed = rand::random::<u32>();
+            let generator = generators
+                .create(DEFAULT_GENERATOR, seed, &ConfigTable::new())
+                .unwrap_or_else(|err| panic!("Failed to create terrain generator: {}", err));
+
+            (seed, generator)
+        });
+
+        WorldMetadata::new(seed, generator, glm::vec3(0.0, 0.0, 20.0))
+    })
+    .unwrap_or_else(|err| panic!("Failed to open world '{}': {}", world_path, err));
+
+    log::info!("Opened world '{}'", world_path.cyan().bold());
+
+    if chosen_terrain.is_some() && !created {
+        log::warn!("The world already exists, so the terrain options are ignored");
+    }
 
     log::info!(
-        "Using noise speed: {}",
-        NOISE_SEED.get().unwrap().cyan().bold()
+        "Using seed {} with the '{}' generator",
+        world_directory.metadata.seed.cyan().bold(),
+        world_directory.metadata.generator.name().cyan().bold()
     );
 
+    // Export the world without opening a window
+    if command_line.exports() {
+        let storage = world_directory.region_storage();
+        run_exports(
+            &command_line,
+            world_directory.metadata.generator.as_ref(),
+            Some(&storage),
+        );
+        return;
+    }
+
     // Initialize GLFW
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
 
@@ -90,45 +244,35 @@ fn main() {
     // Load the shaders
     let shader_program = ShaderProgram::default();
 
-    // Create new chunks
-    let chunks = {
-        let mut chunks = Vec::new();
-        // let gen_strat = ChunkGenStrategy::FlatPlane(voxel::VoxelKind::Grass, 0);
-        let gen_strat = ChunkGenStrategy::Perlin2d;
-
-        for x in -3..4 {
-            for z in -3..4 {
-                let mut chunk = Chunk::new((x, z));
-                gen_strat.apply(&mut chunk);
-                chunks.push(chunk);
-            }
-        }
-
-        chunks
-    };
-
-    info!("Created {} chunks", chunks.len());
-
-    let gen_strat = ChunkGenStrategy::Perlin2d;
-
-    let mut chunk_manager = ChunkManager::new(gen_strat, glm::vec3(0.0, 0.0, 0.0));
+    // Send the block textures to the GPU
+    let texture_array = textures.upload();
 
     // Create transformations
-    let mut camera = Camera::new(glm::vec3(0.0, 0.0, 20.0), 45.0);
+    let mut camera = Camera::new(world_directory.metadata.camera_position, 45.0);
+    camera.set_rotation(
+        world_directory.metadata.camera_yaw,
+        world_directory.metadata.camera_pitch,
+    );
     let projection_matrix = camera.get_projection_matrix(ASPECT_RATIO);
 
+    let generator = world_directory.metadata.generator.clone();
+
+    let storage = world_directory.region_storage();
+    let mut chunk_manager = ChunkManager::new(generator, storage, camera.position);
+    chunk_manager.meshing_mode = command_line.meshing;
+
     // Track delta time
     let mut delta_time;
     let mut last_frame = 0.0f32;
 
     let mut wire_frame = false;
 
-    let mesh = MeshBuilder::new().build_mesh(&chunks);
     let light_pos = glm::vec3(0.0, 30.0, 0.0);
 
     // Loop until the user closes the window
     while !window.should_close() {
         chunk_manager.update(camera.position);
+        chunk_manager.update_meshes();
 
         // Swap front and back buffers
         window.swap_buffers();
@@ -164,19 +308,17 @@ fn main() {
             shader_program.set_uniform("cameraPosition", camera.position);
             shader_program.set_uniform("lightPosition", light_pos);
 
-            get_gl_error!("Uniforms");
+            texture_array.bind(0);
+            shader_program.set_uniform("blockTextures", 0);
 
-            mesh.vao.unwrap().bind();
-            mesh.ibo.unwrap().bind();
+            get_gl_error!("Uniforms");
 
-            get_gl_error!("Bind VAO and IBO");
+            for mesh in chunk_manager.meshes() {
+                let (x, y, z) = mesh.origin;
+                shader_program.set_uniform("chunkOffset", (x as f32, y as f32, z as f32));
 
-            gl::DrawElements(
-                gl::TRIANGLES,
-                mesh.indices.len() as i32,
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-            );
+                mesh.draw();
+            }
 
             get_gl_error!("Draw elements");
         }
@@ -220,4 +362,277 @@ fn main() {
             };
         }
     }
+
+    // Write back any edits and the camera's position before exiting
+    chunk_manager.save_all();
+
+    world_directory.metadata.camera_position = camera.position;
+    world_directory.metadata.camera_yaw = camera.yaw;
+    world_directory.metadata.camera_pitch = camera.pitch;
+
+    if let Err(err) = world_directory.save() {
+        log::error!("Failed to save world metadata: {}", err);
+    }
+}
+
+/// Writes every export asked for on the command line. Chunks saved in the
+/// storage are exported as they are, and the rest come from the generator.
+fn run_exports(
+    command_line: &CommandLine,
+    generator: &dyn TerrainGenerator,
+    storage: Option<&RegionStorage>,
+) {
+    if let Some(path) = &command_line.export_map {
+        match save_map(generator, command_line.map_options, path) {
+            Ok(()) => info!("Exported map to '{}'", path.cyan().bold()),
+            Err(err) => log::error!("Failed to export map to '{}': {}", path, err),
+        }
+    }
+
+    // The other exports need the blocks of the world
+    let positions = export_chunk_positions(command_line);
+    if positions.is_empty() {
+        return;
+    }
+
+    let world = build_world(generator, storage, &positions);
+
+    if let Some((path, format)) = &command_line.export_mesh {
+        export_mesh(&world, command_line.meshing, path, *format);
+    }
+
+    if let Some(selection) = command_line.selection {
+        if let Some(path) = &command_line.export_vox {
+            export_vox(&world, selection, path);
+        }
+
+        if let Some(path) = &command_line.export_schem {
+            export_schem(&world, selection, path);
+        }
+    }
+}
+
+/// Returns the chunks needed by the exports on the command line: the ones
+/// around the origin for meshes, and the ones covering the selection.
+fn export_chunk_positions(command_line: &CommandLine) -> Vec<(i32, i32)> {
+    let mut positions = BTreeSet::new();
+
+    if command_line.export_mesh.is_some() {
+        for x in -MESH_EXPORT_RADIUS..=MESH_EXPORT_RADIUS {
+            for z in -MESH_EXPORT_RADIUS..=MESH_EXPORT_RADIUS {
+                positions.insert((x, z));
+            }
+        }
+    }
+
+    if let Some(selection) = command_line.selection {
+        positions.extend(selection_chunk_positions(selection));
+    }
+
+    positions.into_iter().collect()
+}
+
+/// Returns the positions of every chunk that contains part of the selection.
+fn selection_chunk_positions(selection: Selection) -> Vec<(i32, i32)> {
+    let (min_x, min_z) = world_to_chunk_position(selection.min.0, selection.min.2);
+    let (max_x, max_z) = world_to_chunk_position(selection.max.0, selection.max.2);
+
+    (min_x..=max_x)
+        .flat_map(|x| (min_z..=max_z).map(move |z| (x, z)))
+        .collect()
+}
+
+/// Loads a chunk from the storage, or returns `None` if it hasn't been saved.
+fn load_saved_chunk(storage: Option<&RegionStorage>, pos: (i32, i32)) -> Option<Chunk> {
+    match storage?.load_chunk(pos) {
+        Ok(chunk) => chunk,
+        Err(err) => {
+            log::error!("Failed to load chunk {:?}, regenerating it: {}", pos, err);
+            None
+        }
+    }
+}
+
+/// Builds the chunks at the given positions. Chunks saved in the storage are
+/// loaded with their edits, and only the missing ones are generated.
+///
+/// Generated chunks get the decorations of their neighbours, like they would
+/// in the game: from the neighbours that haven't been saved, and from the
+/// decorations still waiting in the storage. Saved chunks already have theirs.
+fn build_world(
+    generator: &dyn TerrainGenerator,
+    storage: Option<&RegionStorage>,
+    positions: &[(i32, i32)],
+) -> World {
+    let mut world = World::new();
+    let ctx = GenContext::new(block_registry());
+    let mut generated = HashSet::new();
+    let mut decorations = Vec::new();
+
+    for &pos in positions {
+        let chunk = match load_saved_chunk(storage, pos) {
+            Some(chunk) => chunk,
+            None => {
+                let mut chunk = Chunk::new(pos);
+                generator.generate(&mut chunk, &ctx);
+                decorations.extend(generator.decorate(&mut chunk, &ctx));
+                generated.insert(pos);
+
+                chunk
+            }
+        };
+
+        world.insert(chunk);
+    }
+
+    // Decorations can reach into generated chunks from the chunks around them
+    let neighbours = generated
+        .iter()
+        .flat_map(|&(cx, cz)| {
+            (-1..=1).flat_map(move |dx| (-1..=1).map(move |dz| (cx + dx, cz + dz)))
+        })
+        .filter(|pos| !world.contains_chunk(*pos))
+        .collect::<BTreeSet<_>>();
+
+    for pos in neighbours {
+        if load_saved_chunk(storage, pos).is_none() {
+            let mut chunk = Chunk::new(pos);
+            generator.generate(&mut chunk, &ctx);
+            decorations.extend(generator.decorate(&mut chunk, &ctx));
+        }
+    }
+
+    if let Some(storage) = storage {
+        match storage.load_pending_decorations() {
+            Ok(placements) => decorations.extend(placements),
+            Err(err) => log::error!("Failed to load pending decorations: {}", err),
+        }
+    }
+
+    for placement in decorations {
+        let pos = placement.chunk_position();
+
+        if generated.contains(&pos) {
+            if let Some(chunk) = world.get_chunk_mut(pos) {
+                place_in_chunk(chunk, &placement);
+            }
+        }
+    }
+
+    info!(
+        "Loaded {} chunks and generated {}",
+        world.len() - generated.len(),
+        generated.len()
+    );
+
+    world
+}
+
+/// Builds the mesh of the world and writes it to a file in the given format.
+/// Doesn't need a GL context.
+fn export_mesh(world: &World, mode: MeshingMode, path: &str, format: MeshFormat) {
+    let mesh = MeshBuilder::with_mode(mode).build(world);
+
+    match format.save(&mesh, path) {
+        Ok(()) => info!(
+            "Exported {} vertices to '{}'",
+            mesh.vertices.len(),
+            path.cyan().bold()
+        ),
+        Err(err) => log::error!("Failed to export mesh to '{}': {}", path, err),
+    }
+}
+
+/// Writes the selected blocks to a MagicaVoxel .vox file, coloured by the
+/// default palette mapping.
+fn export_vox(world: &World, selection: Selection, path: &str) {
+    let result = VoxMapping::load(VOX_MAPPING_PATH).and_then(|mapping| {
+        let model = VoxModel::from_world(world, selection, &mapping)?;
+        let file = VoxFile {
+            models: vec![model],
+            palette: mapping.palette(),
+        };
+
+        file.save(path).map_err(|err| err.to_string())
+    });
+
+    match result {
+        Ok(()) => info!("Exported selection to '{}'", path.cyan().bold()),
+        Err(err) => log::error!("Failed to export selection to '{}': {}", path, err),
+    }
+}
+
+/// Writes the selected blocks to a Sponge .schem file.
+fn export_schem(world: &World, selection: Selection, path: &str) {
+    let result = Schematic::from_world(world, selection)
+        .and_then(|schematic| schematic.save(path).map_err(|err| err.to_string()));
+
+    match result {
+        Ok(()) => info!("Exported selection to '{}'", path.cyan().bold()),
+        Err(err) => log::error!("Failed to export selection to '{}': {}", path, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{systems::generators::generator_registry, voxel::VoxelKind};
+
+    #[test]
+    fn selections_cover_the_chunks_they_touch() {
+        let selection = Selection::new((-1, 0, 15), (16, 10, 16));
+
+        assert_eq!(
+            selection_chunk_positions(selection),
+            vec![(-1, 0), (-1, 1), (0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn exports_keep_saved_chunks_and_generate_the_rest() {
+        crate::init_test_globals();
+
+        let directory = std::env::temp_dir().join(format!("export_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let storage = RegionStorage::new(&directory);
+
+        let mut params = ConfigTable::new();
+        params.insert("block", "dirt");
+        params.insert("height", 12i64);
+        let generator = generator_registry()
+            .create("flat_plane", 7, &params)
+            .unwrap();
+
+        let stone = block_registry().by_name("stone").unwrap();
+        let dirt = block_registry().by_name("dirt").unwrap();
+
+        // An edited chunk, saved by an earlier session
+        let mut edited = Chunk::new((40, -40));
+        edited.set((3, 30, 4), stone);
+        storage.save_chunk(&edited).unwrap();
+
+        // A selection far from the origin, across the saved chunk and a missing one
+        let selection = Selection::new((40 * 16, 0, -40 * 16), (41 * 16 + 3, 40, -40 * 16 + 8));
+        let positions = selection_chunk_positions(selection);
+        let world = build_world(generator.as_ref(), Some(&storage), &positions);
+
+        let kind = |x, y, z| {
+            world
+                .get_block(x, y, z)
+                .map_or(VoxelKind::AIR, |voxel| voxel.kind)
+        };
+
+        assert_eq!(world.len(), 2);
+
+        // The saved chunk keeps its edit, and isn't regenerated
+        let (x, y, z) = edited.world_position((3, 30, 4));
+        assert_eq!(kind(x, y, z), stone);
+        assert_eq!(kind(x, 5, z), VoxelKind::AIR);
+
+        // The missing chunk comes from the generator
+        assert_eq!(kind(41 * 16 + 2, 12, -40 * 16 + 5), dirt);
+        assert_eq!(kind(41 * 16 + 2, 13, -40 * 16 + 5), VoxelKind::AIR);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
 }
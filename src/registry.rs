@@ -0,0 +1,248 @@
+#![allow(dead_code)]
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    block_state::{BlockProperty, BlockState},
+    config::{Config, ConfigTable},
+    rendering::{mesh::FaceDirection, shapes::block_shape::BlockShape},
+    voxel::{Voxel, VoxelKind},
+    BLOCK_REGISTRY,
+};
+
+/// The default location of the block definitions.
+pub const BLOCK_REGISTRY_PATH: &str = "./assets/blocks.toml";
+
+/// The highest light level a block can emit.
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+/// Describes a single type of block.
+#[derive(Debug, Clone)]
+pub struct BlockDefinition {
+    /// The compact ID stored in each voxel.
+    pub id: VoxelKind,
+
+    /// The unique name of the block.
+    pub name: String,
+
+    /// Whether the block can be collided with.
+    pub solid: bool,
+
+    /// Whether the faces of neighbouring blocks can be seen through this block.
+    pub transparent: bool,
+
+    /// The texture name of each face, indexed by `FaceDirection::index`.
+    pub textures: [Option<String>; 6],
+
+    /// The colour the block is multiplied by.
+    pub tint: (f32, f32, f32),
+
+    /// The light level the block emits, from 0 to `MAX_LIGHT_LEVEL`.
+    pub light_emission: u8,
+
+    /// The geometry of the block.
+    pub shape: BlockShape,
+
+    /// The state properties the block has.
+    pub properties: Vec<BlockProperty>,
+}
+
+impl BlockDefinition {
+    /// Parses a block definition from a `[[block]]` table.
+    fn from_table(table: &ConfigTable) -> Result<Self, String> {
+        let id = table
+            .get_integer("id")
+            .ok_or("Block is missing an integer 'id'")?;
+        let id = u16::try_from(id).map_err(|_| format!("Block ID {} is out of range", id))?;
+
+        let name = table
+            .get_str("name")
+            .ok_or_else(|| format!("Block {} is missing a 'name'", id))?
+            .to_string();
+
+        // The most specific texture wins
+        let all = table.get_str("texture");
+        let side = table.get_str("texture_side").or(all);
+
+        let textures = FaceDirection::all().map(|face| {
+            let key = format!("texture_{}", face.name());
+            let fallback = match face {
+                FaceDirection::Up | FaceDirection::Down => all,
+                _ => side,
+            };
+
+            table.get_str(&key).or(fallback).map(str::to_string)
+        });
+
+        let tint = match table.get_floats("tint").as_deref() {
+            Some([r, g, b]) => (*r as f32, *g as f32, *b as f32),
+            Some(_) => return Err(format!("Block '{}' has an invalid 'tint'", name)),
+            None => (1.0, 1.0, 1.0),
+        };
+
+        let light_emission = table.get_integer("light").unwrap_or(0);
+        if !(0..=MAX_LIGHT_LEVEL as i64).contains(&light_emission) {
+            return Err(format!(
+                "Block '{}' has a light level outside of 0-{}",
+                name, MAX_LIGHT_LEVEL
+            ));
+        }
+
+        let shape = match table.get_str("shape") {
+            Some(shape) => BlockShape::from_name(shape)
+                .ok_or_else(|| format!("Block '{}' has an unknown shape '{}'", name, shape))?,
+            None => BlockShape::Cube,
+        };
+
+        let mut properties = Vec::new();
+        for property in table.get_strs("properties").unwrap_or_default() {
+            properties.push(BlockProperty::from_name(property).ok_or_else(|| {
+                format!("Block '{}' has an unknown property '{}'", name, property)
+            })?);
+        }
+
+        // The shape can't change with the state unless the block has the property
+        if let Some(property) = shape.property() {
+            if !properties.contains(&property) {
+                properties.push(property);
+            }
+        }
+
+        Ok(Self {
+            id: VoxelKind(id),
+            name,
+            solid: table.get_bool("solid").unwrap_or(true),
+            transparent: table.get_bool("transparent").unwrap_or(false),
+            textures,
+            tint,
+            light_emission: light_emission as u8,
+            shape,
+            properties,
+        })
+    }
+
+    /// Returns true if the block fully hides the faces of its neighbours.
+    pub fn occludes(&self) -> bool {
+        !self.transparent && self.shape == BlockShape::Cube
+    }
+
+    /// Returns true if the block has the given state property.
+    pub fn has_property(&self, property: BlockProperty) -> bool {
+        self.properties.contains(&property)
+    }
+
+    /// Creates a voxel of this block type, dropping any properties it doesn't have.
+    pub fn voxel(&self, state: BlockState) -> Voxel {
+        Voxel::with_state(self.id, state.retain(&self.properties))
+    }
+
+    /// Returns the texture name of the given face.
+    pub fn texture(&self, face: FaceDirection) -> Option<&str> {
+        self.textures[face.index()].as_deref()
+    }
+}
+
+/// Stores the definitions of all block types, loaded from an asset file.
+#[derive(Debug)]
+pub struct BlockRegistry {
+    /// The definitions, indexed by their ID.
+    blocks: Vec<Option<BlockDefinition>>,
+
+    /// Maps the name of each block to its ID.
+    names: HashMap<String, VoxelKind>,
+}
+
+impl BlockRegistry {
+    /// Loads the block registry from the given file.
+    /// Panics if the file can not be read, or contains invalid definitions.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        Config::load(path)
+            .and_then(|config| Self::from_config(&config))
+            .unwrap_or_else(|err| panic!("Failed to load block registry: {}", err))
+    }
+
+    /// Creates a block registry from the `[[block]]` tables of a config.
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut registry = Self {
+            blocks: Vec::new(),
+            names: HashMap::new(),
+        };
+
+        for table in config.arrays.get("block").into_iter().flatten() {
+            registry.register(BlockDefinition::from_table(table)?)?;
+        }
+
+        match registry.get(VoxelKind::AIR) {
+            Some(air) if !air.solid && air.transparent => Ok(registry),
+            _ => Err("Block 0 must be a non-solid, transparent air block".to_string()),
+        }
+    }
+
+    /// Adds a block definition to the registry.
+    pub fn register(&mut self, definition: BlockDefinition) -> Result<(), String> {
+        let index = definition.id.0 as usize;
+
+        if self.blocks.get(index).is_some_and(Option::is_some) {
+            return Err(format!("Duplicate block ID {}", index));
+        }
+        if self.names.contains_key(&definition.name) {
+            return Err(format!("Duplicate block name '{}'", definition.name));
+        }
+
+        if self.blocks.len() <= index {
+            self.blocks.resize(index + 1, None);
+        }
+
+        self.names.insert(definition.name.clone(), definition.id);
+        self.blocks[index] = Some(definition);
+
+        Ok(())
+    }
+
+    /// Gets the definition of a block.
+    pub fn get(&self, kind: VoxelKind) -> Option<&BlockDefinition> {
+        self.blocks.get(kind.0 as usize)?.as_ref()
+    }
+
+    /// Gets the ID of a block from its name.
+    pub fn by_name(&self, name: &str) -> Option<VoxelKind> {
+        self.names.get(name).copied()
+    }
+
+    /// Gets the ID of a block from its name.
+    /// Panics if no block has the given name.
+    pub fn kind(&self, name: &str) -> VoxelKind {
+        self.by_name(name)
+            .unwrap_or_else(|| panic!("Unknown block '{}'", name))
+    }
+
+    /// Iterates over all block definitions, ordered by ID.
+    pub fn iter(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.blocks.iter().flatten()
+    }
+
+    /// Returns true if the faces behind the block can be seen.
+    /// Unknown blocks are treated as opaque.
+    pub fn is_transparent(&self, kind: VoxelKind) -> bool {
+        self.get(kind).is_some_and(|block| block.transparent)
+    }
+
+    /// Returns true if the block fully hides the faces of its neighbours.
+    /// Unknown blocks are treated as full cubes.
+    pub fn occludes(&self, kind: VoxelKind) -> bool {
+        self.get(kind).is_none_or(BlockDefinition::occludes)
+    }
+
+    /// Returns true if the block can be collided with.
+    pub fn is_solid(&self, kind: VoxelKind) -> bool {
+        self.get(kind).is_some_and(|block| block.solid)
+    }
+}
+
+/// Returns the global block registry.
+/// Panics if the registry has not been loaded yet.
+pub fn block_registry() -> &'static BlockRegistry {
+    BLOCK_REGISTRY
+        .get()
+        .expect("The block registry has not been loaded")
+}
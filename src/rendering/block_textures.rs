@@ -0,0 +1,331 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use gl::types::{GLuint, GLvoid};
+
+use crate::{
+    registry::BlockRegistry, rendering::mesh::FaceDirection, voxel::VoxelKind, BLOCK_TEXTURES,
+};
+
+/// The default directory block textures are loaded from, as `<name>.png`.
+pub const BLOCK_TEXTURE_DIRECTORY: &str = "./assets/image";
+
+/// The width and height of every block texture, in pixels.
+pub const TEXTURE_SIZE: u32 = 16;
+
+/// The most layers there can be, as packed vertices only have room for this many.
+pub const MAX_LAYERS: usize = 1 << 12;
+
+/// The layer shown on the faces of unknown blocks. It is plain magenta.
+pub const MISSING_LAYER: u32 = 0;
+
+/// The textures of every block face, stacked into the layers of a single
+/// array texture. Faces without a texture image show a plain layer of their
+/// block's tint. Doesn't need a GL context until it is uploaded.
+#[derive(Debug)]
+pub struct BlockTextures {
+    /// The layer of each face of each block, indexed by the block's ID and
+    /// then by `FaceDirection::index`.
+    faces: Vec<[u32; 6]>,
+
+    /// The RGBA pixels of every layer, one after another.
+    pixels: Vec<u8>,
+}
+
+impl BlockTextures {
+    /// Loads the image of every texture named by the block definitions from
+    /// the given directory. Textures without an image are left out, so their
+    /// faces show the block's tint instead.
+    pub fn load(directory: impl AsRef<Path>, registry: &BlockRegistry) -> Result<Self, String> {
+        let names = registry
+            .iter()
+            .flat_map(|block| FaceDirection::all().map(|face| block.texture(face)))
+            .flatten()
+            .collect::<BTreeSet<_>>();
+
+        let layer_size = (TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize;
+
+        let mut textures = Self {
+            faces: Vec::new(),
+            pixels: [255, 0, 255, 255].repeat(layer_size / 4),
+        };
+
+        let mut layers = HashMap::new();
+        let mut missing = Vec::new();
+
+        for name in names {
+            let path = directory.as_ref().join(format!("{}.png", name));
+
+            if !path.exists() {
+                missing.push(name);
+                continue;
+            }
+
+            // Images start at the top, while texture coordinates start at the bottom
+            let image = image::open(&path)
+                .map_err(|err| format!("Failed to open '{}': {}", path.display(), err))?
+                .flipv()
+                .into_rgba8();
+
+            if image.dimensions() != (TEXTURE_SIZE, TEXTURE_SIZE) {
+                return Err(format!(
+                    "Texture '{}' must be {}x{} pixels",
+                    name, TEXTURE_SIZE, TEXTURE_SIZE
+                ));
+            }
+
+            layers.insert(name, textures.push_layer(image.as_raw())?);
+        }
+
+        // Faces without an image share a plain layer with every other face of
+        // the same colour
+        let mut tints = HashMap::new();
+
+        for block in registry.iter() {
+            let index = block.id.0 as usize;
+            if textures.faces.len() <= index {
+                textures.faces.resize(index + 1, [MISSING_LAYER; 6]);
+            }
+
+            for face in FaceDirection::all() {
+                let layer = match block.texture(face).and_then(|name| layers.get(name)) {
+                    Some(layer) => *layer,
+                    None => {
+                        let (r, g, b) = block.tint;
+                        let color =
+                            [r, g, b, 1.0].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+
+                        match tints.get(&color) {
+                            Some(layer) => *layer,
+                            None => {
+                                let layer = textures.push_layer(&color.repeat(layer_size / 4))?;
+                                tints.insert(color, layer);
+                                layer
+                            }
+                        }
+                    }
+                };
+
+                textures.faces[index][face.index()] = layer;
+            }
+        }
+
+        if !missing.is_empty() {
+            log::warn!(
+                "No images for the textures {}, so they only show their block's tint",
+                missing.join(", ")
+            );
+        }
+
+        Ok(textures)
+    }
+
+    /// Adds a layer with the given pixels, returning its index.
+    fn push_layer(&mut self, pixels: &[u8]) -> Result<u32, String> {
+        let layer = self.layer_count();
+        if layer >= MAX_LAYERS {
+            return Err(format!("There can't be more than {} layers", MAX_LAYERS));
+        }
+
+        self.pixels.extend_from_slice(pixels);
+        Ok(layer as u32)
+    }
+
+    /// Returns the layer shown on a face of a block.
+    pub fn face_layer(&self, kind: VoxelKind, face: FaceDirection) -> u32 {
+        self.faces
+            .get(kind.0 as usize)
+            .map_or(MISSING_LAYER, |faces| faces[face.index()])
+    }
+
+    /// Returns the number of layers, including the missing layer.
+    pub fn layer_count(&self) -> usize {
+        self.pixels.len() / (TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize
+    }
+
+    /// Creates the array texture on the GPU. Requires a GL context.
+    pub fn upload(&self) -> TextureArray {
+        let mut id: GLuint = 0;
+
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, id);
+
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::RGBA8 as i32,
+                TEXTURE_SIZE as i32,
+                TEXTURE_SIZE as i32,
+                self.layer_count() as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                self.pixels.as_ptr() as *const GLvoid,
+            );
+
+            // Keep the pixels sharp up close, and repeat the textures across
+            // faces that were merged together
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MAG_FILTER,
+                gl::NEAREST as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_MIN_FILTER,
+                gl::NEAREST_MIPMAP_LINEAR as i32,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+
+            gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+        }
+
+        TextureArray { id }
+    }
+}
+
+/// An array texture on the GPU, deleted when dropped.
+#[derive(Debug)]
+pub struct TextureArray {
+    /// The OpenGL texture ID
+    pub id: GLuint,
+}
+
+impl TextureArray {
+    /// Binds the texture to the given texture unit.
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
+        }
+    }
+}
+
+impl Drop for TextureArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// The axes that texture coordinates run along on each face, indexed by
+/// `FaceDirection::index`. Textures repeat once per block. Seen from outside
+/// of the face, u runs to the right and v runs up, with the side faces going
+/// up along the y axis. Also used by `vertex.glsl`, through
+/// `face_uv_axes_glsl`.
+pub const FACE_UV_AXES: [[(f32, f32, f32); 2]; 6] = [
+    [(1.0, 0.0, 0.0), (0.0, 0.0, -1.0)],
+    [(1.0, 0.0, 0.0), (0.0, 0.0, 1.0)],
+    [(0.0, 0.0, 1.0), (0.0, 1.0, 0.0)],
+    [(0.0, 0.0, -1.0), (0.0, 1.0, 0.0)],
+    [(-1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+    [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+];
+
+/// Returns the texture coordinates of a point on a face.
+pub fn face_uv((x, y, z): (f32, f32, f32), face: FaceDirection) -> (f32, f32) {
+    let [u, v] = FACE_UV_AXES[face.index()];
+    let dot = |(a, b, c): (f32, f32, f32)| a * x + b * y + c * z;
+
+    (dot(u), dot(v))
+}
+
+/// Returns `FACE_UV_AXES` as a GLSL array of vec3s, with the u and v axes of
+/// each face one after another.
+pub fn face_uv_axes_glsl() -> String {
+    let axes = FACE_UV_AXES
+        .iter()
+        .flatten()
+        .map(|(x, y, z)| format!("vec3({:?}, {:?}, {:?})", x, y, z))
+        .collect::<Vec<_>>();
+
+    format!("vec3[]({})", axes.join(", "))
+}
+
+/// Returns the global block textures.
+/// Panics if the textures have not been loaded yet.
+pub fn block_textures() -> &'static BlockTextures {
+    BLOCK_TEXTURES
+        .get()
+        .expect("The block textures have not been loaded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::shader::{shader::insert_defines, shader_program::VERTEX_SHADER_PATH};
+
+    #[test]
+    fn the_vertex_shader_uses_the_uv_axes() {
+        assert_eq!(
+            face_uv_axes_glsl()[..60],
+            *"vec3[](vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0), vec3(1.0, "
+        );
+
+        let source = std::fs::read_to_string(VERTEX_SHADER_PATH).unwrap();
+        let source = insert_defines(&source, &[("FACE_UV_AXES", face_uv_axes_glsl())]);
+
+        let mut lines = source.lines();
+        assert_eq!(lines.next(), Some("#version 410 core"));
+        assert_eq!(
+            lines.next(),
+            Some(format!("#define FACE_UV_AXES {}", face_uv_axes_glsl()).as_str())
+        );
+        assert!(source.contains("const vec3 UV_AXES[12] = FACE_UV_AXES;"));
+    }
+
+    #[test]
+    fn textures_are_never_mirrored() {
+        for face in FaceDirection::all() {
+            // How far u and v move for a step along each axis
+            let steps = [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)]
+                .map(|position| face_uv(position, face));
+            let u = (steps[0].0, steps[1].0, steps[2].0);
+            let v = (steps[0].1, steps[1].1, steps[2].1);
+
+            // u x v points out of the face when the texture isn't mirrored
+            let cross = (
+                u.1 * v.2 - u.2 * v.1,
+                u.2 * v.0 - u.0 * v.2,
+                u.0 * v.1 - u.1 * v.0,
+            );
+            assert_eq!(cross, face.normal(), "{:?}", face);
+
+            if !matches!(face, FaceDirection::Up | FaceDirection::Down) {
+                assert_eq!(v, (0.0, 1.0, 0.0), "{:?}", face);
+            }
+        }
+    }
+
+    #[test]
+    fn side_textures_run_the_same_way_around_a_block() {
+        // Returns which quarter of a texture is shown at a point, for a
+        // texture with a different colour in each quarter
+        let quarter = |position, face| {
+            let (u, v) = face_uv(position, face);
+            (u.rem_euclid(1.0) >= 0.5, v.rem_euclid(1.0) >= 0.5)
+        };
+
+        // Points near the bottom left and bottom right of each side face of
+        // the block at the origin, as seen from outside of the face
+        let sides = [
+            (FaceDirection::Front, (0.9, 0.1, 0.0), (0.1, 0.1, 0.0)),
+            (FaceDirection::Right, (1.0, 0.1, 0.9), (1.0, 0.1, 0.1)),
+            (FaceDirection::Back, (0.1, 0.1, 1.0), (0.9, 0.1, 1.0)),
+            (FaceDirection::Left, (0.0, 0.1, 0.1), (0.0, 0.1, 0.9)),
+        ];
+
+        for (face, left, right) in sides {
+            assert_eq!(quarter(left, face), (false, false), "{:?}", face);
+            assert_eq!(quarter(right, face), (true, false), "{:?}", face);
+        }
+    }
+}
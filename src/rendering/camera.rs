@@ -50,12 +50,13 @@ impl Camera {
 
     /// Rotates the camera by the given yaw and pitch
     pub fn rotate(&mut self, yaw: f32, pitch: f32) {
-        self.yaw += yaw;
-        self.pitch += pitch;
+        self.set_rotation(self.yaw + yaw, self.pitch + pitch);
+    }
 
-        self.pitch = self
-            .pitch
-            .clamp(-89.0f32.to_radians(), 89.0f32.to_radians());
+    /// Sets the yaw and pitch of the camera
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-89.0f32.to_radians(), 89.0f32.to_radians());
 
         self.front = glm::vec3(
             self.yaw.cos() * self.pitch.cos(),
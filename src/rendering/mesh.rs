@@ -1,34 +1,195 @@
+use std::collections::BTreeMap;
+
 use crate::{
-    chunk::Chunk,
-    utils::{world_to_chunk_coordinate, world_to_chunk_position},
-    voxel::VoxelKind, buffers::{vao::Vao, vbo::Vbo, ibo::Ibo, vao_builder::VaoBuilder}, get_gl_error,
+    block_state::BlockProperty,
+    buffers::{ibo::Ibo, vao::Vao, vao_builder::VaoBuilder, vbo::Vbo},
+    chunk::{Chunk, CHUNK_WIDTH, SECTIONS_PER_CHUNK, SECTION_HEIGHT},
+    get_gl_error,
+    registry::block_registry,
+    rendering::{
+        block_textures::{block_textures, face_uv, MISSING_LAYER},
+        shapes::block_shape::{BlockBox, BlockShape},
+    },
+    voxel::{Voxel, VoxelKind},
+    world::World,
 };
 
-/// A vertex that can be passed to the GPU.
-#[repr(C)]
+/// A vertex of a mesh. It is packed into a `PackedVertex` when the mesh is
+/// uploaded.
 #[derive(Clone, Copy, Debug)]
 pub struct Vertex {
     /// The position of the vertex.
     pub position: (f32, f32, f32),
-    /// The normal of the vertex.
-    pub normal: (f32, f32, f32),
+    /// The direction of the face the vertex belongs to.
+    pub direction: FaceDirection,
+    /// The light emitted by the block, from 0 to `registry::MAX_LIGHT_LEVEL`.
+    pub light: u8,
+    /// The ambient occlusion level of the vertex, from 0 to `UNOCCLUDED`.
+    pub occlusion: u8,
+    /// The layer of the block textures the face shows.
+    pub texture: u32,
+}
+
+impl Vertex {
+    /// Returns the normal of the vertex.
+    pub const fn normal(&self) -> (f32, f32, f32) {
+        self.direction.normal()
+    }
+
+    /// Returns the texture coordinates of the vertex, with textures repeating
+    /// once per block and v running up.
+    pub fn uv(&self) -> (f32, f32) {
+        face_uv(self.position, self.direction)
+    }
+}
+
+/// How many steps each block is split into by the positions of packed
+/// vertices. This is fine enough for the corners of every block shape.
+pub const POSITION_SCALE: f32 = 64.0;
+
+/// A vertex packed into two integers, as it is passed to the GPU. Its position
+/// is stored relative to the origin of its mesh, in steps of
+/// 1/`POSITION_SCALE` of a block, and decoded in `vertex.glsl`.
+///
+/// From the lowest bit, the first integer holds 11 bits of x, 11 bits of z,
+/// 3 bits of the face direction's index, 2 bits of ambient occlusion and 4
+/// bits of light. The second holds 14 bits of y and 12 bits of texture layer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedVertex(pub u32, pub u32);
+
+impl PackedVertex {
+    /// Packs a vertex, relative to the origin of its mesh.
+    /// Panics if the vertex is too far from the origin, or its light, ambient
+    /// occlusion or texture layer doesn't fit in its bits.
+    pub fn pack(vertex: &Vertex, origin: (i32, i32, i32)) -> Self {
+        assert!(
+            vertex.occlusion <= UNOCCLUDED && vertex.light < 1 << 4 && vertex.texture < 1 << 12,
+            "Vertex {:?} can't be packed",
+            vertex
+        );
+
+        let coordinate = |value: f32, start: i32, bits: u32| {
+            let steps = ((value - start as f32) * POSITION_SCALE).round();
+
+            assert!(
+                (0.0..(1u32 << bits) as f32).contains(&steps),
+                "Vertex {:?} is too far from the origin {:?}",
+                vertex.position,
+                origin
+            );
+            steps as u32
+        };
+
+        let (x, y, z) = vertex.position;
+        let (origin_x, origin_y, origin_z) = origin;
+
+        Self(
+            coordinate(x, origin_x, 11)
+                | coordinate(z, origin_z, 11) << 11
+                | (vertex.direction.index() as u32) << 22
+                | (vertex.occlusion as u32) << 25
+                | (vertex.light as u32) << 27,
+            coordinate(y, origin_y, 14) | vertex.texture << 14,
+        )
+    }
 }
 
 /// A mesh that can be passed to the GPU.
+/// The buffers are only created once the mesh is uploaded.
 #[derive(Clone, Debug)]
 pub struct Mesh {
     /// The vertices of the mesh.
     pub vertices: Vec<Vertex>,
+    /// The position the vertices are packed relative to. Only meshes that fit
+    /// in a chunk above it can be uploaded.
+    pub origin: (i32, i32, i32),
     /// The indices of the mesh.
     pub indices: Vec<u32>,
+    /// The block type of each quad, used to group faces into materials.
+    pub kinds: Vec<VoxelKind>,
     /// The VAO of the mesh.
     pub vao: Option<Vao>,
     /// The VBO of the mesh.
-    pub vbo: Option<Vbo<Vertex>>,
+    pub vbo: Option<Vbo<PackedVertex>>,
     /// The IBO of the mesh.
     pub ibo: Option<Ibo>,
 }
 
+impl Mesh {
+    /// Creates the GPU buffers of the mesh, packing its vertices.
+    /// Requires a GL context.
+    pub fn upload(&mut self) {
+        let vertices = self
+            .vertices
+            .iter()
+            .map(|vertex| PackedVertex::pack(vertex, self.origin))
+            .collect::<Vec<_>>();
+
+        let vbo = Vbo::new(&vertices, gl::STATIC_DRAW);
+        vbo.bind();
+        self.vbo = Some(vbo);
+
+        get_gl_error!("Mesh VBO");
+
+        self.vao = Some(VaoBuilder::new().add_layer::<u32>(2).build());
+
+        get_gl_error!("Mesh VAO");
+
+        self.ibo = Some(Ibo::new(&self.indices, gl::STATIC_DRAW));
+
+        assert!(self.indices.len() % 3 == 0);
+        get_gl_error!("Mesh IBO");
+    }
+
+    /// Deletes the GPU buffers of the mesh, if it has been uploaded.
+    /// Requires a GL context.
+    pub fn release(&mut self) {
+        if let Some(vao) = self.vao.take() {
+            vao.delete();
+        }
+        if let Some(vbo) = self.vbo.take() {
+            vbo.delete();
+        }
+        if let Some(ibo) = self.ibo.take() {
+            ibo.delete();
+        }
+    }
+
+    /// Draws the mesh with the current shader program, which must have its
+    /// `chunkOffset` set to the mesh's origin.
+    /// Does nothing if the mesh hasn't been uploaded.
+    pub fn draw(&self) {
+        let (vao, ibo) = match (self.vao, self.ibo) {
+            (Some(vao), Some(ibo)) => (vao, ibo),
+            _ => return,
+        };
+
+        vao.bind();
+        ibo.bind();
+
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                self.indices.len() as i32,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+
+    /// Returns the indices of the mesh, grouped by the block type of their quad.
+    pub fn indices_by_kind(&self) -> BTreeMap<VoxelKind, Vec<u32>> {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+
+        for (quad, kind) in self.indices.chunks_exact(6).zip(self.kinds.iter()) {
+            groups.entry(*kind).or_default().extend_from_slice(quad);
+        }
+
+        groups
+    }
+}
+
 /// The different directions that a face can be facing.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FaceDirection {
@@ -53,6 +214,44 @@ impl FaceDirection {
         ]
     }
 
+    /// Returns the index of the direction in `FaceDirection::all`.
+    pub const fn index(&self) -> usize {
+        match self {
+            FaceDirection::Up => 0,
+            FaceDirection::Down => 1,
+            FaceDirection::Left => 2,
+            FaceDirection::Right => 3,
+            FaceDirection::Front => 4,
+            FaceDirection::Back => 5,
+        }
+    }
+
+    /// Returns the lowercase name of the direction.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            FaceDirection::Up => "up",
+            FaceDirection::Down => "down",
+            FaceDirection::Left => "left",
+            FaceDirection::Right => "right",
+            FaceDirection::Front => "front",
+            FaceDirection::Back => "back",
+        }
+    }
+
+    /// Returns the position of the neighbouring block in the direction.
+    pub const fn neighbour(&self, position: (i32, i32, i32)) -> (i32, i32, i32) {
+        let (x, y, z) = position;
+
+        match self {
+            FaceDirection::Up => (x, y + 1, z),
+            FaceDirection::Down => (x, y - 1, z),
+            FaceDirection::Left => (x - 1, y, z),
+            FaceDirection::Right => (x + 1, y, z),
+            FaceDirection::Front => (x, y, z - 1),
+            FaceDirection::Back => (x, y, z + 1),
+        }
+    }
+
     /// Returns the face normal of the direction.
     pub const fn normal(&self) -> (f32, f32, f32) {
         match self {
@@ -66,155 +265,601 @@ impl FaceDirection {
     }
 }
 
+/// How the faces of full cubes are turned into quads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshingMode {
+    /// One quad for every visible face.
+    Naive,
+    /// Neighbouring faces of the same block are merged into larger rectangles.
+    Greedy,
+}
+
+impl MeshingMode {
+    /// Returns the lowercase name of the mode.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            MeshingMode::Naive => "naive",
+            MeshingMode::Greedy => "greedy",
+        }
+    }
+
+    /// Gets a mode from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [MeshingMode::Naive, MeshingMode::Greedy]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+/// The ambient occlusion of a vertex that no blocks are darkening. How much
+/// each level darkens a vertex is decided by `vertex.glsl`.
+pub const UNOCCLUDED: u8 = 3;
+
+/// A visible face of a full cube. Neighbouring faces are only merged when
+/// they are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CubeFace {
+    kind: VoxelKind,
+    /// The layer of the block textures the face shows.
+    texture: u32,
+    /// The ambient occlusion of each vertex.
+    occlusion: [u8; 4],
+}
+
+/// Sections are cubes, so every slice of one is a square of this many blocks.
+const SLICE_SIZE: usize = CHUNK_WIDTH;
+const _: () = assert!(SECTION_HEIGHT == SLICE_SIZE);
+
 /// A struct that builds a mesh from a set of voxels.
 pub struct MeshBuilder {
     /// The mesh that is being built.
     mesh: Mesh,
+    /// How the faces of full cubes are turned into quads.
+    mode: MeshingMode,
 }
 
 impl MeshBuilder {
-    /// Creates a new mesh builder.
-    pub fn new() -> Self {
+    /// Creates a new mesh builder with the given meshing mode.
+    pub fn with_mode(mode: MeshingMode) -> Self {
         Self {
             mesh: Mesh {
                 vertices: Vec::new(),
                 indices: Vec::new(),
+                kinds: Vec::new(),
+                origin: (0, 0, 0),
                 vao: None,
                 vbo: None,
                 ibo: None,
             },
+            mode,
         }
     }
 
-    /// Builds the mesh from a list of chunks
-    pub fn build_mesh(mut self, chunks: &[Chunk]) -> Mesh {
-        let position_offsets = (-1..=1)
-            .flat_map(move |x| (-1..=1).map(move |y| (x, y)))
-            .collect::<Vec<_>>();
-
+    /// Builds the mesh from every chunk in the world, without uploading it.
+    /// Doesn't need a GL context.
+    pub fn build(mut self, world: &World) -> Mesh {
         // Iterate through each chunk
-        for chunk in chunks.iter() {
-            let adjacent_chunks = position_offsets
-                .iter()
-                .map(|(x, y)| (chunk.position.0 + x, chunk.position.1 + y))
-                .map(|(x, y)| chunks.iter().find(|chunk| chunk.position == (x, y)))
-                .flatten()
-                .collect::<Vec<_>>();
-                
+        for chunk in world.chunks() {
             // Go through each block
-            self.build_chunk_mesh(chunk, &adjacent_chunks);
+            self.build_chunk_mesh(chunk, world);
         }
 
-        self.mesh.vbo = Some(Vbo::new(&self.mesh.vertices, gl::STATIC_DRAW));
-        self.mesh.vbo.unwrap().bind();
-
-        get_gl_error!("Mesh VBO");
-
-        self.mesh.vao = Some(VaoBuilder::new()
-        .add_layer::<f32>(3)
-        .add_layer::<f32>(3)
-        .build());
-
-        get_gl_error!("Mesh VAO");
+        self.mesh
+    }
 
-        self.mesh.ibo = Some(Ibo::new(&self.mesh.indices, gl::STATIC_DRAW));
-
-        assert!(self.mesh.indices.len() % 3 == 0);
-        get_gl_error!("Mesh IBO");
+    /// Builds the mesh of a single chunk, without uploading it.
+    /// Neighbouring blocks are looked up through the world.
+    pub fn build_chunk(mut self, chunk: &Chunk, world: &World) -> Mesh {
+        self.mesh.origin = chunk.world_position((0, 0, 0));
+        self.build_chunk_mesh(chunk, world);
 
         self.mesh
     }
 
     /// Builds the mesh for a single chunk.
-    pub fn build_chunk_mesh(&mut self, chunk: &Chunk, adjacent_chunks: &[&Chunk]) {
+    /// Neighbouring blocks are looked up through the world.
+    pub fn build_chunk_mesh(&mut self, chunk: &Chunk, world: &World) {
+        for section in 0..SECTIONS_PER_CHUNK {
+            self.build_section_mesh(chunk, section, world);
+        }
+    }
+
+    /// Builds the mesh for a single section of a chunk.
+    /// Sections that only contain air are skipped.
+    pub fn build_section_mesh(&mut self, chunk: &Chunk, section: usize, world: &World) {
+        if chunk.sections[section].is_empty() {
+            return;
+        }
+
+        let base_y = section * SECTION_HEIGHT;
+
         // Go through each block
-        for (_, voxel) in chunk.blocks.iter() {
-            // If the voxel is air, skip it
-            if voxel.kind == VoxelKind::Air {
-                continue;
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                for y in base_y..base_y + SECTION_HEIGHT {
+                    // If the voxel is air, skip it
+                    let voxel = match chunk.get((x, y, z)) {
+                        Some(voxel) if voxel.kind != VoxelKind::AIR => voxel,
+                        _ => continue,
+                    };
+
+                    let position = chunk.world_position((x, y, z));
+
+                    let shape = block_registry()
+                        .get(voxel.kind)
+                        .map_or(BlockShape::Cube, |block| block.shape);
+
+                    if shape == BlockShape::Liquid {
+                        self.add_liquid(position, voxel, world);
+                        continue;
+                    }
+
+                    if shape != BlockShape::Cube {
+                        self.add_shape(position, voxel, shape, world);
+                        continue;
+                    }
+
+                    // Cubes are merged together once the whole section has been seen
+                    if self.mode == MeshingMode::Greedy {
+                        continue;
+                    }
+
+                    // Add all faces that are not adjacent to another voxel
+                    for direction in FaceDirection::all().iter() {
+                        self.add_quad_if_not_adjacent(position, voxel, *direction, world);
+                    }
+                }
             }
+        }
+
+        if self.mode == MeshingMode::Greedy {
+            self.add_greedy_faces(chunk, section, world);
+        }
+    }
+
+    /// Adds the visible faces of the full cubes in a section, merging
+    /// neighbouring faces of the same block into as few rectangles as possible.
+    /// Each slice of the section is merged on its own, for each direction.
+    fn add_greedy_faces(&mut self, chunk: &Chunk, section: usize, world: &World) {
+        let base_y = section * SECTION_HEIGHT;
+
+        // Maps a position in a slice to a position in the chunk. Slices are
+        // stacked along the direction's axis, and `u` and `v` run across them
+        let to_chunk = |direction: FaceDirection, slice: usize, u: usize, v: usize| match direction
+        {
+            FaceDirection::Up | FaceDirection::Down => (u, base_y + slice, v),
+            FaceDirection::Left | FaceDirection::Right => (slice, base_y + v, u),
+            FaceDirection::Front | FaceDirection::Back => (u, base_y + v, slice),
+        };
+
+        for direction in FaceDirection::all() {
+            for slice in 0..SLICE_SIZE {
+                // Each visible face in the slice, indexed by [v][u]
+                let mut faces = [[None; SLICE_SIZE]; SLICE_SIZE];
+
+                for (v, row) in faces.iter_mut().enumerate() {
+                    for (u, face) in row.iter_mut().enumerate() {
+                        let position = to_chunk(direction, slice, u, v);
+                        *face = self.cube_face(chunk, position, direction, world);
+                    }
+                }
+
+                for v in 0..SLICE_SIZE {
+                    let mut u = 0;
+
+                    while u < SLICE_SIZE {
+                        let face = match faces[v][u] {
+                            Some(face) => face,
+                            None => {
+                                u += 1;
+                                continue;
+                            }
+                        };
+
+                        // Grow the rectangle along the row, then down whole rows. Only
+                        // faces with the same texture and occlusion are merged, so
+                        // that the rectangle looks like each of its faces
+                        let mut width = 1;
+                        while u + width < SLICE_SIZE && faces[v][u + width] == Some(face) {
+                            width += 1;
+                        }
+
+                        let mut height = 1;
+                        while v + height < SLICE_SIZE
+                            && faces[v + height][u..u + width]
+                                .iter()
+                                .all(|other| *other == Some(face))
+                        {
+                            height += 1;
+                        }
+
+                        for row in faces[v..v + height].iter_mut() {
+                            row[u..u + width].fill(None);
+                        }
+
+                        let first = chunk.world_position(to_chunk(direction, slice, u, v));
+                        let last = chunk.world_position(to_chunk(
+                            direction,
+                            slice,
+                            u + width - 1,
+                            v + height - 1,
+                        ));
 
-            // Add all faces that are not adjacent to another voxel
-            for direction in FaceDirection::all().iter() {
-                self.add_quad_if_not_adjacent(voxel.position, *direction, adjacent_chunks);
+                        let min = (first.0 as f32, first.1 as f32, first.2 as f32);
+                        let max = (
+                            (last.0 + 1) as f32,
+                            (last.1 + 1) as f32,
+                            (last.2 + 1) as f32,
+                        );
+
+                        let verticies = Self::get_box_face_verticies(min, max, direction);
+                        self.push_quad(
+                            verticies,
+                            face.kind,
+                            direction,
+                            face.texture,
+                            face.occlusion,
+                        );
+
+                        u += width;
+                    }
+                }
             }
         }
     }
 
+    /// Returns the face of a full cube at a position in the chunk, if its face
+    /// in the given direction is visible.
+    fn cube_face(
+        &self,
+        chunk: &Chunk,
+        position: (usize, usize, usize),
+        direction: FaceDirection,
+        world: &World,
+    ) -> Option<CubeFace> {
+        let voxel = chunk
+            .get(position)
+            .filter(|voxel| voxel.kind != VoxelKind::AIR)?;
+
+        let shape = block_registry()
+            .get(voxel.kind)
+            .map_or(BlockShape::Cube, |block| block.shape);
+
+        let position = chunk.world_position(position);
+
+        if shape != BlockShape::Cube || self.is_adjacent(position, direction, world) {
+            return None;
+        }
+
+        Some(CubeFace {
+            kind: voxel.kind,
+            texture: Self::face_texture(voxel, direction),
+            occlusion: Self::face_occlusion(position, direction, world),
+        })
+    }
+
     /// Checks if a voxel is adjacent to another voxel, in a given direction,
     /// and if not, adds a quad to the mesh.
     fn add_quad_if_not_adjacent(
         &mut self,
         position: (i32, i32, i32),
+        voxel: Voxel,
         direction: FaceDirection,
-        adjacent_chunks: &[&Chunk],
+        world: &World,
+    ) {
+        if !self.is_adjacent(position, direction, world) {
+            let occlusion = Self::face_occlusion(position, direction, world);
+            self.add_quad(position, voxel, direction, occlusion);
+        }
+    }
+
+    /// Adds the faces of a block that isn't a full cube, based on its state.
+    /// Faces inside of the block are always added, while faces on its boundary
+    /// are hidden by adjacent voxels like the faces of a cube.
+    fn add_shape(
+        &mut self,
+        position: (i32, i32, i32),
+        voxel: Voxel,
+        shape: BlockShape,
+        world: &World,
     ) {
-        if !self.is_adjacent(position, direction, adjacent_chunks) {
-            self.add_quad(position, direction);
+        let (x, y, z) = position;
+        let (x, y, z) = (x as f32, y as f32, z as f32);
+
+        let boxes = shape.boxes(voxel.state);
+
+        for (index, (min, max)) in boxes.iter().copied().enumerate() {
+            for direction in FaceDirection::all() {
+                let on_boundary = match direction {
+                    FaceDirection::Up => max.1 >= 1.0,
+                    FaceDirection::Down => min.1 <= 0.0,
+                    FaceDirection::Left => min.0 <= 0.0,
+                    FaceDirection::Right => max.0 >= 1.0,
+                    FaceDirection::Front => min.2 <= 0.0,
+                    FaceDirection::Back => max.2 >= 1.0,
+                };
+
+                if on_boundary && self.is_adjacent(position, direction, world) {
+                    continue;
+                }
+
+                // Only the parts of the face that don't touch another box are visible
+                for (min, max) in Self::uncovered_face_parts(&boxes, index, direction) {
+                    let min = (x + min.0, y + min.1, z + min.2);
+                    let max = (x + max.0, y + max.1, z + max.2);
+
+                    let verticies = Self::get_box_face_verticies(min, max, direction);
+                    let texture = Self::face_texture(voxel, direction);
+                    self.push_quad(verticies, voxel.kind, direction, texture, [UNOCCLUDED; 4]);
+                }
+            }
+        }
+    }
+
+    /// Splits a face of one of a shape's boxes into the parts that aren't
+    /// covered by the other boxes, returned as boxes that share the face.
+    fn uncovered_face_parts(
+        boxes: &[BlockBox],
+        index: usize,
+        direction: FaceDirection,
+    ) -> Vec<BlockBox> {
+        let as_array = |(x, y, z): (f32, f32, f32)| [x, y, z];
+        let as_tuple = |[x, y, z]: [f32; 3]| (x, y, z);
+
+        let (axis, positive) = match direction {
+            FaceDirection::Left => (0, false),
+            FaceDirection::Right => (0, true),
+            FaceDirection::Down => (1, false),
+            FaceDirection::Up => (1, true),
+            FaceDirection::Front => (2, false),
+            FaceDirection::Back => (2, true),
+        };
+
+        let (min, max) = boxes[index];
+        let (min, max) = (as_array(min), as_array(max));
+        let plane = if positive { max[axis] } else { min[axis] };
+
+        let mut parts = vec![(min, max)];
+
+        for (other, (other_min, other_max)) in boxes.iter().enumerate() {
+            let (other_min, other_max) = (as_array(*other_min), as_array(*other_max));
+
+            // Only boxes on the other side of the face's plane can cover it
+            let touches = if positive {
+                other_min[axis] == plane
+            } else {
+                other_max[axis] == plane
+            };
+
+            if other == index || !touches {
+                continue;
+            }
+
+            parts = parts
+                .into_iter()
+                .flat_map(|(min, max)| {
+                    let overlaps = (0..3)
+                        .filter(|i| *i != axis)
+                        .all(|i| other_min[i] < max[i] && min[i] < other_max[i]);
+
+                    if !overlaps {
+                        return vec![(min, max)];
+                    }
+
+                    // Cut off the parts on either side of the other box, one
+                    // axis at a time, leaving the covered part in the middle
+                    let mut remaining = (min, max);
+                    let mut uncovered = Vec::new();
+
+                    for i in (0..3).filter(|i| *i != axis) {
+                        let (mut min, mut max) = remaining;
+
+                        if min[i] < other_min[i] {
+                            let mut below = remaining;
+                            below.1[i] = other_min[i];
+                            uncovered.push(below);
+                            min[i] = other_min[i];
+                        }
+
+                        if other_max[i] < max[i] {
+                            let mut above = (min, max);
+                            above.0[i] = other_max[i];
+                            uncovered.push(above);
+                            max[i] = other_max[i];
+                        }
+
+                        remaining = (min, max);
+                    }
+
+                    uncovered
+                })
+                .collect();
         }
+
+        parts
+            .into_iter()
+            .map(|(min, max)| (as_tuple(min), as_tuple(max)))
+            .collect()
     }
 
-    /// Checks if a voxel is adjacent to another voxel, in a given direction.
+    /// Adds the faces of a liquid. Faces touching the same liquid are hidden,
+    /// and its surface is only lowered when there isn't more of it above.
+    fn add_liquid(&mut self, position: (i32, i32, i32), voxel: Voxel, world: &World) {
+        let is_same_liquid = |(x, y, z)| {
+            world
+                .get_block(x, y, z)
+                .is_some_and(|other| other.kind == voxel.kind)
+        };
+
+        let (x, y, z) = position;
+        let (x, y, z) = (x as f32, y as f32, z as f32);
+
+        for (min, mut max) in BlockShape::Liquid.boxes(voxel.state) {
+            if is_same_liquid(FaceDirection::Up.neighbour(position)) {
+                max.1 = 1.0;
+            }
+
+            for direction in FaceDirection::all() {
+                if is_same_liquid(direction.neighbour(position)) {
+                    continue;
+                }
+
+                // A lowered surface is never hidden by the block above it
+                let on_boundary = direction != FaceDirection::Up || max.1 >= 1.0;
+                if on_boundary && self.is_adjacent(position, direction, world) {
+                    continue;
+                }
+
+                let min = (x + min.0, y + min.1, z + min.2);
+                let max = (x + max.0, y + max.1, z + max.2);
+
+                let verticies = Self::get_box_face_verticies(min, max, direction);
+                let texture = Self::face_texture(voxel, direction);
+                self.push_quad(verticies, voxel.kind, direction, texture, [UNOCCLUDED; 4]);
+            }
+        }
+    }
+
+    /// Checks if a voxel is adjacent to another voxel that hides its face, in a given direction.
     fn is_adjacent(
         &self,
         position: (i32, i32, i32),
         direction: FaceDirection,
-        adjacent_chunks: &[&Chunk],
+        world: &World,
     ) -> bool {
+        let (bx, by, bz) = direction.neighbour(position);
+
+        // Blocks above or below the world, or in chunks that haven't been built, are treated as air
+        world
+            .get_block(bx, by, bz)
+            .is_some_and(|voxel| block_registry().occludes(voxel.kind))
+    }
+
+    /// Returns the ambient occlusion of each vertex of a cube's face, in the
+    /// same order as `get_face_verticies`, from 0 to `UNOCCLUDED`.
+    ///
+    /// Each vertex is darkened by the blocks in front of the face that touch
+    /// its corner: the two beside it, and the one diagonally across from it.
+    fn face_occlusion(
+        position: (i32, i32, i32),
+        direction: FaceDirection,
+        world: &World,
+    ) -> [u8; 4] {
         let (x, y, z) = position;
+        let (fx, fy, fz) = direction.neighbour(position);
 
-        let (bx, by, bz) = match direction {
-            FaceDirection::Up => (x, y + 1, z),
-            FaceDirection::Down => (x, y - 1, z),
-            FaceDirection::Left => (x - 1, y, z),
-            FaceDirection::Right => (x + 1, y, z),
-            FaceDirection::Front => (x, y, z - 1),
-            FaceDirection::Back => (x, y, z + 1),
+        let occludes = |(dx, dy, dz): (i32, i32, i32)| {
+            world
+                .get_block(fx + dx, fy + dy, fz + dz)
+                .is_some_and(|voxel| block_registry().occludes(voxel.kind))
         };
 
-        // Because adjacent chunks also includes *this* chunk,
-        // we can just check if the block exists in the adjacent chunks
-        let chunk_pos = world_to_chunk_position(bx, bz);
-        let chunk_coords = world_to_chunk_coordinate(bx, by, bz);
+        Self::get_face_verticies(position, direction).map(|(vx, vy, vz)| {
+            // Which way the corner lies along each axis across the face
+            let step = |vertex: i32, block: i32, front: i32| {
+                if front != block {
+                    0
+                } else if vertex > block {
+                    1
+                } else {
+                    -1
+                }
+            };
 
-        let chunk = match adjacent_chunks
-            .iter()
-            .find(|chunk| matches!(chunk, chunk if chunk.position == chunk_pos))
-            .cloned()
-        {
-            Some(chunk) => chunk,
-            None => return false,
+            let mut sides = [
+                (step(vx, x, fx), 0, 0),
+                (0, step(vy, y, fy), 0),
+                (0, 0, step(vz, z, fz)),
+            ]
+            .into_iter()
+            .filter(|side| *side != (0, 0, 0));
+
+            let first = sides.next().expect("Faces span two axes");
+            let second = sides.next().expect("Faces span two axes");
+            let corner = (first.0 + second.0, first.1 + second.1, first.2 + second.2);
+
+            // Two blocks at the sides hide the corner whether or not it is filled
+            let (first, second, corner) = (occludes(first), occludes(second), occludes(corner));
+            if first && second {
+                0
+            } else {
+                UNOCCLUDED - first as u8 - second as u8 - corner as u8
+            }
+        })
+    }
+
+    /// Returns the layer of the block textures shown on a face of a block.
+    /// Blocks that can face different directions show the face of their model
+    /// that was rotated onto it.
+    fn face_texture(voxel: Voxel, direction: FaceDirection) -> u32 {
+        let block = match block_registry().get(voxel.kind) {
+            Some(block) => block,
+            None => return MISSING_LAYER,
+        };
+
+        let face = if block.has_property(BlockProperty::Facing) {
+            voxel.state.facing().model_face(direction)
+        } else {
+            direction
         };
-            
 
-        // Check if the block exists
-        chunk.blocks[&chunk_coords].kind != VoxelKind::Air
+        block_textures().face_layer(voxel.kind, face)
+    }
+
+    /// Adds a quad to the mesh, textured by the block's definition and shaded
+    /// by the ambient occlusion of each of its vertices.
+    pub fn add_quad(
+        &mut self,
+        position: (i32, i32, i32),
+        voxel: Voxel,
+        direction: FaceDirection,
+        occlusion: [u8; 4],
+    ) {
+        let verticies = Self::get_face_verticies(position, direction)
+            .map(|(x, y, z)| (x as f32, y as f32, z as f32));
+        let texture = Self::face_texture(voxel, direction);
+
+        self.push_quad(verticies, voxel.kind, direction, texture, occlusion);
     }
 
-    /// Adds a quad to the mesh.
-    pub fn add_quad(&mut self, position: (i32, i32, i32), direction: FaceDirection) {
+    /// Adds a quad with the given vertices to the mesh, showing a layer of the
+    /// block textures and shaded by the ambient occlusion of each vertex.
+    fn push_quad(
+        &mut self,
+        verticies: [(f32, f32, f32); 4],
+        kind: VoxelKind,
+        direction: FaceDirection,
+        texture: u32,
+        occlusion: [u8; 4],
+    ) {
         // Add the indices
-        let index_offset = match self.mesh.indices.last() {
-            Some(last) => *last + 4,
-            None => 0,
-        };
+        let index_offset = self.mesh.vertices.len() as u32;
 
-        self.mesh.indices.push(index_offset);
-        self.mesh.indices.push(index_offset + 1);
-        self.mesh.indices.push(index_offset + 2);
+        // The quad is split along the diagonal between its brighter corners, so a
+        // dark corner doesn't bleed across the whole face
+        let corners = if occlusion[0] + occlusion[2] < occlusion[1] + occlusion[3] {
+            [1, 2, 3, 3, 0, 1]
+        } else {
+            [0, 1, 2, 2, 3, 0]
+        };
 
-        self.mesh.indices.push(index_offset + 2);
-        self.mesh.indices.push(index_offset + 3);
-        self.mesh.indices.push(index_offset);
+        for corner in corners {
+            self.mesh.indices.push(index_offset + corner);
+        }
+        self.mesh.kinds.push(kind);
 
         // Add the vertices
-        let verticies = Self::get_face_verticies(position, direction);
-        let normal = direction.normal();
+        let light = block_registry()
+            .get(kind)
+            .map_or(0, |block| block.light_emission);
 
-        for vertex in verticies.iter() {
+        for (vertex, occlusion) in verticies.iter().zip(occlusion) {
             self.mesh.vertices.push(Vertex {
-                position: (vertex.0 as f32, vertex.1 as f32, vertex.2 as f32),
-                normal,
+                position: *vertex,
+                direction,
+                light,
+                occlusion,
+                texture,
             });
         }
     }
@@ -273,4 +918,412 @@ impl MeshBuilder {
                 ],
         }
     }
+
+    /// Returns the vertices of a face of an axis-aligned box, given its min and max
+    /// corners. The vertices are in the same order as `get_face_verticies`.
+    #[rustfmt::skip]
+    pub const fn get_box_face_verticies(
+        min: (f32, f32, f32),
+        max: (f32, f32, f32),
+        direction: FaceDirection,
+    ) -> [(f32, f32, f32); 4] {
+        let (x0, y0, z0) = min;
+        let (x1, y1, z1) = max;
+
+        match direction {
+            FaceDirection::Up => [(x0, y1, z1), (x0, y1, z0), (x1, y1, z0), (x1, y1, z1)],
+            FaceDirection::Down => [(x0, y0, z1), (x0, y0, z0), (x1, y0, z0), (x1, y0, z1)],
+            FaceDirection::Left => [(x0, y1, z1), (x0, y0, z1), (x0, y0, z0), (x0, y1, z0)],
+            FaceDirection::Right => [(x1, y1, z1), (x1, y0, z1), (x1, y0, z0), (x1, y1, z0)],
+            FaceDirection::Front => [(x0, y1, z0), (x0, y0, z0), (x1, y0, z0), (x1, y1, z0)],
+            FaceDirection::Back => [(x0, y1, z1), (x0, y0, z1), (x1, y0, z1), (x1, y1, z1)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        block_state::{BlockState, Facing, Half},
+        chunk::Chunk,
+        config::ConfigTable,
+        systems::generators::{generator_registry, GenContext},
+        voxel::Voxel,
+    };
+
+    /// A face of a mesh: its block, direction, and the corners of its square
+    /// in 1/`POSITION_SCALE` units.
+    type Face = (VoxelKind, FaceDirection, [i32; 3], [i32; 3]);
+
+    /// Unpacks a vertex the way `vertex.glsl` does, into its position relative
+    /// to the origin in 1/`POSITION_SCALE` steps, face, ambient occlusion,
+    /// light and texture layer.
+    fn unpack(PackedVertex(a, b): PackedVertex) -> ((u32, u32, u32), u32, u32, u32, u32) {
+        (
+            (a & 0x7FF, b & 0x3FFF, (a >> 11) & 0x7FF),
+            (a >> 22) & 0x7,
+            (a >> 25) & 0x3,
+            (a >> 27) & 0xF,
+            (b >> 14) & 0xFFF,
+        )
+    }
+
+    /// A vertex at the given offset from the origin, in 1/`POSITION_SCALE` steps.
+    fn vertex_at(origin: (i32, i32, i32), (x, y, z): (u32, u32, u32)) -> Vertex {
+        let offset = |origin: i32, steps: u32| origin as f32 + steps as f32 / POSITION_SCALE;
+
+        Vertex {
+            position: (
+                offset(origin.0, x),
+                offset(origin.1, y),
+                offset(origin.2, z),
+            ),
+            direction: FaceDirection::Up,
+            light: 0,
+            occlusion: 0,
+            texture: 0,
+        }
+    }
+
+    #[test]
+    fn vertices_pack_and_unpack_at_their_limits() {
+        let origin = (-32, 0, 48);
+
+        let mut highest = vertex_at(origin, (2047, 16383, 2047));
+        highest.direction = FaceDirection::Back;
+        highest.occlusion = UNOCCLUDED;
+        highest.light = 15;
+        highest.texture = 4095;
+
+        let packed = PackedVertex::pack(&highest, origin);
+        assert_eq!(unpack(packed), ((2047, 16383, 2047), 5, 3, 15, 4095));
+        // Every bit is set, other than the middle bit of the face and the unused top bits
+        assert_eq!(packed, PackedVertex(0x7F7F_FFFF, 0x03FF_FFFF));
+
+        let lowest = vertex_at(origin, (0, 0, 0));
+        assert_eq!(PackedVertex::pack(&lowest, origin), PackedVertex(0, 0));
+
+        // Each field on its own doesn't spill into the others
+        for face in FaceDirection::all() {
+            let mut vertex = vertex_at(origin, (1, 2, 3));
+            vertex.direction = face;
+
+            let unpacked = unpack(PackedVertex::pack(&vertex, origin));
+            assert_eq!(unpacked, ((1, 2, 3), face.index() as u32, 0, 0, 0));
+        }
+
+        let mut vertex = vertex_at(origin, (0, 0, 0));
+        vertex.texture = 4095;
+        assert_eq!(
+            unpack(PackedVertex::pack(&vertex, origin)),
+            ((0, 0, 0), 0, 0, 0, 4095)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "too far from the origin")]
+    fn packing_a_vertex_past_the_x_limit_panics() {
+        PackedVertex::pack(&vertex_at((0, 0, 0), (2048, 0, 0)), (0, 0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "too far from the origin")]
+    fn packing_a_vertex_past_the_y_limit_panics() {
+        PackedVertex::pack(&vertex_at((0, 0, 0), (0, 16384, 0)), (0, 0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "too far from the origin")]
+    fn packing_a_vertex_below_the_origin_panics() {
+        PackedVertex::pack(&vertex_at((0, 0, 0), (0, 0, 0)), (0, 0, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be packed")]
+    fn packing_a_texture_layer_past_the_limit_panics() {
+        let mut vertex = vertex_at((0, 0, 0), (0, 0, 0));
+        vertex.texture = 4096;
+
+        PackedVertex::pack(&vertex, (0, 0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be packed")]
+    fn packing_a_light_level_past_the_limit_panics() {
+        let mut vertex = vertex_at((0, 0, 0), (0, 0, 0));
+        vertex.light = 16;
+
+        PackedVertex::pack(&vertex, (0, 0, 0));
+    }
+
+    /// Builds the mesh of a stone block at (5, 10, 5) with stone at each of
+    /// the given positions, and returns the ambient occlusion of the corners
+    /// of its top face and the corners its two triangles are made of.
+    fn top_face_occlusion(neighbours: &[(i32, i32, i32)]) -> ([u8; 4], [u32; 6]) {
+        crate::init_test_globals();
+        let stone = block_registry().kind("stone");
+
+        let mut world = World::new();
+        world.insert(Chunk::new((0, 0)));
+        world.set_block(5, 10, 5, stone);
+        for (x, y, z) in neighbours.iter().copied() {
+            world.set_block(x, y, z, stone);
+        }
+
+        let mesh = MeshBuilder::with_mode(MeshingMode::Naive).build(&world);
+
+        let quad = mesh
+            .vertices
+            .chunks(4)
+            .position(|quad| {
+                quad[0].direction == FaceDirection::Up && quad[0].position == (5.0, 11.0, 6.0)
+            })
+            .expect("The block's top face is visible");
+
+        let vertices = &mesh.vertices[quad * 4..quad * 4 + 4];
+        let positions = vertices
+            .iter()
+            .map(|vertex| vertex.position)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [
+                (5.0, 11.0, 6.0),
+                (5.0, 11.0, 5.0),
+                (6.0, 11.0, 5.0),
+                (6.0, 11.0, 6.0)
+            ]
+        );
+
+        let occlusion = [0, 1, 2, 3].map(|corner| vertices[corner].occlusion);
+        let corners = std::array::from_fn(|i| mesh.indices[quad * 6 + i] - quad as u32 * 4);
+
+        (occlusion, corners)
+    }
+
+    #[test]
+    fn corners_are_darkened_by_the_blocks_around_them() {
+        // Nothing above the face
+        assert_eq!(top_face_occlusion(&[]), ([3; 4], [0, 1, 2, 2, 3, 0]));
+
+        // A block diagonally across from the second corner
+        assert_eq!(
+            top_face_occlusion(&[(4, 11, 4)]),
+            ([3, 2, 3, 3], [0, 1, 2, 2, 3, 0])
+        );
+
+        // Blocks on both sides of the second corner hide it completely, and
+        // each darken one other corner
+        assert_eq!(
+            top_face_occlusion(&[(4, 11, 5), (5, 11, 4)]),
+            ([2, 0, 2, 3], [0, 1, 2, 2, 3, 0])
+        );
+
+        // A block beside the third and fourth corners, and one across from the third
+        assert_eq!(
+            top_face_occlusion(&[(6, 11, 5), (6, 11, 4)]),
+            ([3, 3, 1, 2], [1, 2, 3, 3, 0, 1])
+        );
+    }
+
+    #[test]
+    fn quads_are_split_between_their_brighter_corners() {
+        // With the first corner darkest, the diagonal runs between the second
+        // and fourth corners instead
+        assert_eq!(
+            top_face_occlusion(&[(4, 11, 6)]),
+            ([2, 3, 3, 3], [1, 2, 3, 3, 0, 1])
+        );
+
+        assert_eq!(
+            top_face_occlusion(&[(4, 11, 6), (6, 11, 4)]),
+            ([2, 3, 2, 3], [1, 2, 3, 3, 0, 1])
+        );
+
+        // A tie keeps the first diagonal
+        assert_eq!(
+            top_face_occlusion(&[(4, 11, 6), (6, 11, 6)]),
+            ([2, 3, 3, 2], [0, 1, 2, 2, 3, 0])
+        );
+    }
+
+    /// Generates a square of chunks around the origin.
+    fn generate_world(generator: &str, params: &ConfigTable) -> World {
+        crate::init_test_globals();
+
+        let generator = generator_registry().create(generator, 9, params).unwrap();
+        let ctx = GenContext::new(block_registry());
+
+        let mut world = World::new();
+        for x in -1..2 {
+            for z in -1..2 {
+                let mut chunk = Chunk::new((x, z));
+                generator.generate(&mut chunk, &ctx);
+                world.insert(chunk);
+            }
+        }
+
+        world
+    }
+
+    /// Splits the quads of a mesh into the unit faces they cover. Quads that
+    /// don't lie on whole blocks, such as those of slabs, are kept whole.
+    fn unit_faces(mesh: &Mesh) -> Vec<Face> {
+        let mut faces = Vec::new();
+
+        for (quad, kind) in mesh.vertices.chunks(4).zip(mesh.kinds.iter()) {
+            let corner = |pick: fn(f32, f32) -> f32| {
+                let (x, y, z) = quad
+                    .iter()
+                    .map(|vertex| vertex.position)
+                    .fold(quad[0].position, |a, b| {
+                        (pick(a.0, b.0), pick(a.1, b.1), pick(a.2, b.2))
+                    });
+
+                [x, y, z].map(|c| (c * POSITION_SCALE) as i32)
+            };
+
+            let (min, max) = (corner(f32::min), corner(f32::max));
+            let direction = quad[0].direction;
+
+            let unit = POSITION_SCALE as i32;
+            if min.iter().chain(max.iter()).any(|c| c % unit != 0) {
+                faces.push((*kind, direction, min, max));
+                continue;
+            }
+
+            // The axis the face points along has no extent, so it's stepped once
+            let steps =
+                |axis: usize| (min[axis]..max[axis].max(min[axis] + 1)).step_by(unit as usize);
+
+            for x in steps(0) {
+                for y in steps(1) {
+                    for z in steps(2) {
+                        let mut end = [x, y, z];
+
+                        for axis in 0..3 {
+                            if min[axis] != max[axis] {
+                                end[axis] += unit;
+                            }
+                        }
+
+                        faces.push((*kind, direction, [x, y, z], end));
+                    }
+                }
+            }
+        }
+
+        faces
+    }
+
+    #[test]
+    fn greedy_meshes_cover_the_same_faces() {
+        let mut flat = ConfigTable::new();
+        flat.insert("block", "dirt");
+        flat.insert("height", 20i64);
+
+        for (generator, params) in [
+            ("flat_plane", flat),
+            ("perlin_2d", ConfigTable::new()),
+            ("biomes", ConfigTable::new()),
+        ] {
+            let world = generate_world(generator, &params);
+
+            let naive = unit_faces(&MeshBuilder::with_mode(MeshingMode::Naive).build(&world));
+            let greedy = unit_faces(&MeshBuilder::with_mode(MeshingMode::Greedy).build(&world));
+
+            let naive_set = naive.iter().copied().collect::<HashSet<_>>();
+            let greedy_set = greedy.iter().copied().collect::<HashSet<_>>();
+
+            // No face is covered twice, and both cover the same faces
+            assert_eq!(naive_set.len(), naive.len(), "{}", generator);
+            assert_eq!(greedy_set.len(), greedy.len(), "{}", generator);
+            assert!(naive_set == greedy_set, "{}", generator);
+        }
+    }
+
+    #[test]
+    fn greedy_meshes_have_fewer_triangles_on_flat_terrain() {
+        let mut params = ConfigTable::new();
+        params.insert("block", "stone");
+        params.insert("height", 20i64);
+
+        let world = generate_world("flat_plane", &params);
+
+        let naive = MeshBuilder::with_mode(MeshingMode::Naive).build(&world);
+        let greedy = MeshBuilder::with_mode(MeshingMode::Greedy).build(&world);
+
+        assert!(!greedy.indices.is_empty());
+        assert!(greedy.indices.len() < naive.indices.len());
+
+        // Each 16x16 slice of a section merges into one quad, so the top of
+        // the plane is one quad per chunk
+        let up = |mesh: &Mesh| {
+            mesh.vertices
+                .chunks(4)
+                .filter(|quad| quad[0].direction == FaceDirection::Up)
+                .count()
+        };
+
+        assert_eq!(up(&naive), 9 * CHUNK_WIDTH * CHUNK_WIDTH);
+        assert_eq!(up(&greedy), 9);
+    }
+
+    /// Meshes a single block standing on its own, returning the number of
+    /// quads and their total area.
+    fn mesh_single_block(voxel: Voxel) -> (usize, f32) {
+        let mut world = World::new();
+        world.insert(Chunk::new((0, 0)));
+        world.set_block(4, 10, 4, voxel);
+
+        let mesh = MeshBuilder::with_mode(MeshingMode::Naive).build(&world);
+
+        let area = mesh
+            .vertices
+            .chunks(4)
+            .map(|quad| {
+                let length = |a: &Vertex, b: &Vertex| {
+                    let (ax, ay, az) = a.position;
+                    let (bx, by, bz) = b.position;
+                    ((ax - bx).powi(2) + (ay - by).powi(2) + (az - bz).powi(2)).sqrt()
+                };
+
+                length(&quad[0], &quad[1]) * length(&quad[1], &quad[2])
+            })
+            .sum();
+
+        (mesh.kinds.len(), area)
+    }
+
+    #[test]
+    fn stairs_only_show_their_outside() {
+        crate::init_test_globals();
+        let stairs = block_registry().kind("stone_stairs");
+
+        for facing in [Facing::North, Facing::South, Facing::East, Facing::West] {
+            let state = BlockState::default().with_facing(facing);
+            let (quads, area) = mesh_single_block(Voxel::with_state(stairs, state));
+
+            // Six faces for the bottom box, where the top is cut in half by
+            // the step, and five for the step, which sits on the bottom box
+            assert_eq!(quads, 11, "{:?}", facing);
+            assert_eq!(area, 5.5, "{:?}", facing);
+        }
+    }
+
+    #[test]
+    fn slabs_only_show_their_outside() {
+        crate::init_test_globals();
+        let slab = block_registry().kind("stone_slab");
+
+        for half in [Half::Lower, Half::Upper] {
+            let state = BlockState::default().with_half(half);
+            let (quads, area) = mesh_single_block(Voxel::with_state(slab, state));
+
+            assert_eq!((quads, area), (6, 4.0), "{:?}", half);
+        }
+    }
 }
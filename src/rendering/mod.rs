@@ -1,3 +1,4 @@
+pub mod block_textures;
 pub mod camera;
 pub mod mesh;
 pub mod shader;
@@ -8,6 +8,8 @@ pub struct Shader {
     id: u32,
     kind: ShaderKind,
     path: &'static str,
+    /// Macros defined at the top of the source, as (name, value) pairs.
+    defines: Vec<(&'static str, String)>,
 }
 
 pub enum ShaderKind {
@@ -16,8 +18,17 @@ pub enum ShaderKind {
 }
 
 impl Shader {
-    pub fn new(path: &'static str, kind: ShaderKind) -> Shader {
-        Shader { id: 0, path, kind }
+    pub fn new(
+        path: &'static str,
+        kind: ShaderKind,
+        defines: Vec<(&'static str, String)>,
+    ) -> Shader {
+        Shader {
+            id: 0,
+            path,
+            kind,
+            defines,
+        }
     }
 
     pub fn compile(&mut self) {
@@ -30,6 +41,7 @@ impl Shader {
             let source = fs::read_to_string(self.path)
                 .unwrap_or_else(|_| panic!("Failed to read shader file, {}", self.path));
 
+            let source = insert_defines(&source, &self.defines);
             let source = CString::new(source.as_bytes()).unwrap();
 
             // Compile the shader
@@ -67,6 +79,20 @@ impl Shader {
     }
 }
 
+/// Defines the given macros after the `#version` line of a shader's source,
+/// which has to come first.
+pub fn insert_defines(source: &str, defines: &[(&str, String)]) -> String {
+    let (version, rest) = source.split_once('\n').unwrap_or((source, ""));
+    let mut result = format!("{}\n", version);
+
+    for (name, value) in defines {
+        result.push_str(&format!("#define {} {}\n", name, value));
+    }
+
+    result.push_str(rest);
+    result
+}
+
 impl Drop for Shader {
     fn drop(&mut self) {
         unsafe {
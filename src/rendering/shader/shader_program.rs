@@ -6,7 +6,15 @@ use owo_colors::OwoColorize;
 
 use nalgebra_glm as glm;
 
-use crate::rendering::shader::shader::{Shader, ShaderKind};
+use crate::rendering::{
+    block_textures::face_uv_axes_glsl,
+    shader::shader::{Shader, ShaderKind},
+};
+
+/// The default vertex shader.
+pub const VERTEX_SHADER_PATH: &str = "./assets/shaders/vertex.glsl";
+/// The default fragment shader.
+pub const FRAGMENT_SHADER_PATH: &str = "./assets/shaders/frag.glsl";
 
 pub struct ShaderProgram {
     id: u32,
@@ -29,11 +37,21 @@ impl ShaderProgram {
     pub fn new(
         vertex_shader_path: &'static str,
         fragment_shader_path: &'static str,
+    ) -> ShaderProgram {
+        Self::with_defines(vertex_shader_path, fragment_shader_path, Vec::new())
+    }
+
+    /// Creates a new shader program from the given vertex and fragment
+    /// shaders, with the given macros defined in both of them.
+    pub fn with_defines(
+        vertex_shader_path: &'static str,
+        fragment_shader_path: &'static str,
+        defines: Vec<(&'static str, String)>,
     ) -> ShaderProgram {
         let mut program = ShaderProgram {
             id: 0,
-            vertex_shader: Shader::new(vertex_shader_path, ShaderKind::Vertex),
-            fragment_shader: Shader::new(fragment_shader_path, ShaderKind::Fragment),
+            vertex_shader: Shader::new(vertex_shader_path, ShaderKind::Vertex, defines.clone()),
+            fragment_shader: Shader::new(fragment_shader_path, ShaderKind::Fragment, defines),
         };
 
         program.compile_all();
@@ -124,9 +142,13 @@ impl ShaderProgram {
 
 impl Default for ShaderProgram {
     /// Creates a new shader program from the defeault vertex and fragment shaders.
-    /// (./res/shaders/vertex.glsl, ./res/shaders/frag.glsl)
+    /// (./assets/shaders/vertex.glsl, ./assets/shaders/frag.glsl)
     fn default() -> Self {
-        Self::new("./assets/shaders/vertex.glsl", "./assets/shaders/frag.glsl")
+        Self::with_defines(
+            VERTEX_SHADER_PATH,
+            FRAGMENT_SHADER_PATH,
+            vec![("FACE_UV_AXES", face_uv_axes_glsl())],
+        )
     }
 }
 
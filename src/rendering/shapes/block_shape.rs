@@ -0,0 +1,97 @@
+use crate::block_state::{
+    BlockProperty, BlockState, Facing, Half, MAX_GROWTH_STAGE, MAX_WATER_LEVEL,
+};
+
+/// How high the surface of a full block of liquid is, so that it sits a
+/// little below the blocks around it.
+pub const LIQUID_HEIGHT: f32 = 0.875;
+
+/// An axis-aligned box inside of a block, from its min to max corner.
+/// Coordinates are relative to the block, and range from 0 to 1.
+pub type BlockBox = ((f32, f32, f32), (f32, f32, f32));
+
+/// The geometry of a block type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockShape {
+    /// A full cube.
+    Cube,
+    /// Half of a cube, split horizontally. Uses the `half` property.
+    Slab,
+    /// A slab with a step on top. Uses the `facing` property, and the
+    /// step is on the side the block is facing.
+    Stairs,
+    /// A thin column that grows taller. Uses the `growth_stage` property.
+    Crop,
+    /// A liquid, whose surface sinks as its `water_level` rises. Level 0 is a
+    /// full block. The mesher hides faces between blocks of the same liquid.
+    Liquid,
+}
+
+impl BlockShape {
+    /// Returns the lowercase name of the shape, as used in the block registry.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            BlockShape::Cube => "cube",
+            BlockShape::Slab => "slab",
+            BlockShape::Stairs => "stairs",
+            BlockShape::Crop => "crop",
+            BlockShape::Liquid => "liquid",
+        }
+    }
+
+    /// Gets a shape from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            BlockShape::Cube,
+            BlockShape::Slab,
+            BlockShape::Stairs,
+            BlockShape::Crop,
+            BlockShape::Liquid,
+        ]
+        .into_iter()
+        .find(|shape| shape.name() == name)
+    }
+
+    /// Returns the property the shape depends on, if any.
+    pub const fn property(&self) -> Option<BlockProperty> {
+        match self {
+            BlockShape::Cube => None,
+            BlockShape::Slab => Some(BlockProperty::Half),
+            BlockShape::Stairs => Some(BlockProperty::Facing),
+            BlockShape::Crop => Some(BlockProperty::GrowthStage),
+            BlockShape::Liquid => Some(BlockProperty::WaterLevel),
+        }
+    }
+
+    /// Returns the boxes that make up the shape, given the state of the block.
+    pub fn boxes(&self, state: BlockState) -> Vec<BlockBox> {
+        match self {
+            BlockShape::Cube => vec![((0.0, 0.0, 0.0), (1.0, 1.0, 1.0))],
+            BlockShape::Slab => match state.half() {
+                Half::Lower => vec![((0.0, 0.0, 0.0), (1.0, 0.5, 1.0))],
+                Half::Upper => vec![((0.0, 0.5, 0.0), (1.0, 1.0, 1.0))],
+            },
+            BlockShape::Stairs => {
+                let step = match state.facing() {
+                    Facing::South => ((0.0, 0.5, 0.5), (1.0, 1.0, 1.0)),
+                    Facing::East => ((0.5, 0.5, 0.0), (1.0, 1.0, 1.0)),
+                    Facing::West => ((0.0, 0.5, 0.0), (0.5, 1.0, 1.0)),
+                    // Stairs can't face up or down, so treat them as facing north
+                    _ => ((0.0, 0.5, 0.0), (1.0, 1.0, 0.5)),
+                };
+
+                vec![((0.0, 0.0, 0.0), (1.0, 0.5, 1.0)), step]
+            }
+            BlockShape::Crop => {
+                let height = (state.growth_stage() + 1) as f32 / (MAX_GROWTH_STAGE + 1) as f32;
+
+                vec![((0.25, 0.0, 0.25), (0.75, height, 0.75))]
+            }
+            BlockShape::Liquid => {
+                let height = 1.0 - state.water_level() as f32 / (MAX_WATER_LEVEL + 1) as f32;
+
+                vec![((0.0, 0.0, 0.0), (1.0, height * LIQUID_HEIGHT, 1.0))]
+            }
+        }
+    }
+}
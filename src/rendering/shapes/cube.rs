@@ -3,7 +3,10 @@ use nalgebra_glm as glm;
 use crate::{
     buffers::{ibo::Ibo, vao_builder::VaoBuilder, vbo::Vbo},
     get_gl_error,
-    rendering::mesh::{FaceDirection, Mesh, Vertex},
+    rendering::{
+        block_textures::MISSING_LAYER,
+        mesh::{FaceDirection, Mesh, PackedVertex, Vertex, UNOCCLUDED},
+    },
 };
 
 #[allow(dead_code)]
@@ -67,33 +70,49 @@ impl Cube {
         }
     }
 
-    pub fn generate_mesh(&mut self) {
-        let indices = CUBE_INDICIES.iter().flatten().cloned().collect::<Vec<_>>();
+    /// Returns the vertices of every face of the cube.
+    fn verticies(&self) -> Vec<Vertex> {
         let mut verticies = Vec::new();
 
         for (face, positions) in CUBE_FACES {
-            let normal = face.normal();
-
             for position in positions {
                 let position = glm::vec3(position.0 as f32, position.1 as f32, position.2 as f32)
                     + self.position;
 
                 verticies.push(Vertex {
                     position: (position.x, position.y, position.z),
-                    normal,
+                    direction: face,
+                    light: 0,
+                    occlusion: UNOCCLUDED,
+                    texture: MISSING_LAYER,
                 });
             }
         }
 
-        let vbo = Vbo::new(&verticies, gl::STATIC_DRAW);
+        verticies
+    }
+
+    pub fn generate_mesh(&mut self) {
+        let indices = CUBE_INDICIES.iter().flatten().cloned().collect::<Vec<_>>();
+        let verticies = self.verticies();
+
+        // The vertices are packed relative to the block the cube starts in
+        let origin = (
+            self.position.x.floor() as i32,
+            self.position.y.floor() as i32,
+            self.position.z.floor() as i32,
+        );
+        let packed = verticies
+            .iter()
+            .map(|vertex| PackedVertex::pack(vertex, origin))
+            .collect::<Vec<_>>();
+
+        let vbo = Vbo::new(&packed, gl::STATIC_DRAW);
         vbo.bind();
 
         get_gl_error!("Cube VBO");
 
-        let vao = VaoBuilder::new()
-            .add_layer::<f32>(3)
-            .add_layer::<f32>(3)
-            .build();
+        let vao = VaoBuilder::new().add_layer::<u32>(2).build();
 
         get_gl_error!("Cube VAO");
 
@@ -105,6 +124,8 @@ impl Cube {
         let mesh = Mesh {
             vertices: verticies,
             indices: indices,
+            kinds: Vec::new(),
+            origin,
 
             vao: Some(vao),
             vbo: Some(vbo),
@@ -115,26 +136,17 @@ impl Cube {
     }
 
     pub fn update_vbo(&mut self) {
-        let mut verticies = Vec::new();
-
-        for (face, positions) in CUBE_FACES {
-            let normal = face.normal();
-
-            for position in positions {
-                let position = glm::vec3(position.0 as f32, position.1 as f32, position.2 as f32)
-                    + self.position;
-
-                verticies.push(Vertex {
-                    position: (position.x, position.y, position.z),
-                    normal,
-                });
-            }
-        }
+        let verticies = self.verticies();
 
         if let Some(mesh) = self.mesh.as_mut() {
+            let packed = verticies
+                .iter()
+                .map(|vertex| PackedVertex::pack(vertex, mesh.origin))
+                .collect::<Vec<_>>();
+
             // Update the VBO
             mesh.vbo.unwrap().bind();
-            mesh.vbo.unwrap().update(&verticies);
+            mesh.vbo.unwrap().update(&packed);
 
             mesh.vertices = verticies;
         }
@@ -1 +1,2 @@
+pub mod block_shape;
 pub mod cube;
@@ -0,0 +1,189 @@
+use std::io;
+
+use crate::{
+    block_collections::BlockCollection,
+    block_state::BlockState,
+    chunk::{Chunk, SectionBlocks, SECTIONS_PER_CHUNK},
+    voxel::{Voxel, VoxelKind},
+};
+
+/// The version of the chunk format. Bump this when the layout changes.
+pub const CHUNK_FORMAT_VERSION: u8 = 1;
+
+// The tags that describe how a section is stored.
+const SECTION_EMPTY: u8 = 0;
+const SECTION_UNIFORM: u8 = 1;
+const SECTION_MIXED: u8 = 2;
+
+/// Encodes a chunk into bytes. All numbers are little endian.
+///
+/// The layout is the format version, the chunk position, the number of
+/// sections, and then each section from the bottom up: a tag byte, followed
+/// by a single voxel for uniform sections, or the palette and the packed
+/// block data for mixed sections.
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    bytes.push(CHUNK_FORMAT_VERSION);
+    bytes.extend_from_slice(&chunk.position.0.to_le_bytes());
+    bytes.extend_from_slice(&chunk.position.1.to_le_bytes());
+    bytes.push(SECTIONS_PER_CHUNK as u8);
+
+    for section in chunk.sections.iter() {
+        match &section.blocks {
+            SectionBlocks::Empty => bytes.push(SECTION_EMPTY),
+            SectionBlocks::Uniform(voxel) => {
+                bytes.push(SECTION_UNIFORM);
+                write_voxel(&mut bytes, *voxel);
+            }
+            SectionBlocks::Mixed(blocks) => {
+                bytes.push(SECTION_MIXED);
+                bytes.push(blocks.bits_per_block());
+
+                bytes.extend_from_slice(&(blocks.palette().len() as u32).to_le_bytes());
+                for voxel in blocks.palette() {
+                    write_voxel(&mut bytes, *voxel);
+                }
+
+                bytes.extend_from_slice(&(blocks.data().len() as u32).to_le_bytes());
+                for word in blocks.data() {
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    bytes
+}
+
+/// Decodes a chunk that was encoded with `encode_chunk`.
+/// Sections missing from the data are left empty, so chunks saved with fewer
+/// sections can still be loaded.
+pub fn decode_chunk(bytes: &[u8]) -> io::Result<Chunk> {
+    let mut reader = ByteReader::new(bytes);
+
+    let version = reader.u8()?;
+    if version != CHUNK_FORMAT_VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported chunk format version {}",
+            version
+        )));
+    }
+
+    let position = (reader.i32()?, reader.i32()?);
+    let section_count = reader.u8()? as usize;
+
+    let mut chunk = Chunk::new(position);
+
+    for index in 0..section_count {
+        let blocks = match reader.u8()? {
+            SECTION_EMPTY => SectionBlocks::Empty,
+            SECTION_UNIFORM => SectionBlocks::Uniform(reader.voxel()?),
+            SECTION_MIXED => {
+                let bits_per_block = reader.u8()?;
+
+                let palette_len = reader.u32()? as usize;
+                let palette = (0..palette_len)
+                    .map(|_| reader.voxel())
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                let word_count = reader.u32()? as usize;
+                let data = (0..word_count)
+                    .map(|_| reader.u64())
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                SectionBlocks::Mixed(
+                    BlockCollection::from_raw(palette, bits_per_block, data)
+                        .map_err(invalid_data)?,
+                )
+            }
+            tag => return Err(invalid_data(format!("Unknown section tag {}", tag))),
+        };
+
+        if index >= SECTIONS_PER_CHUNK {
+            if !matches!(blocks, SectionBlocks::Empty) {
+                return Err(invalid_data("Chunk has too many sections".to_string()));
+            }
+            continue;
+        }
+
+        chunk.sections[index].blocks = blocks;
+    }
+
+    if reader.position != bytes.len() {
+        return Err(invalid_data("Unexpected data after chunk".to_string()));
+    }
+
+    Ok(chunk)
+}
+
+/// Writes the kind and state of a voxel.
+pub fn write_voxel(bytes: &mut Vec<u8>, voxel: Voxel) {
+    bytes.extend_from_slice(&voxel.kind.0.to_le_bytes());
+    bytes.extend_from_slice(&voxel.state.0.to_le_bytes());
+}
+
+/// Creates an error for malformed data.
+pub fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads little endian numbers from a byte slice.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Creates a reader starting at the first byte.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// Returns the number of bytes that haven't been read yet.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    /// Reads the next `length` bytes.
+    pub fn slice(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| invalid_data("Unexpected end of data".to_string()))?;
+
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn voxel(&mut self) -> io::Result<Voxel> {
+        Ok(Voxel::with_state(
+            VoxelKind(self.u16()?),
+            BlockState(self.u16()?),
+        ))
+    }
+}
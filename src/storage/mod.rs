@@ -0,0 +1,6 @@
+// Persists worlds to disk.
+// Chunks are encoded into a compact binary format, and grouped into region files,
+// next to a metadata file describing the rest of the world.
+pub mod chunk_format;
+pub mod region;
+pub mod world_meta;
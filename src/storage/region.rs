@@ -0,0 +1,475 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+use crate::{chunk::Chunk, systems::generators::decorations::Placement};
+
+use super::chunk_format::{decode_chunk, encode_chunk, invalid_data, write_voxel, ByteReader};
+
+/// The number of chunks along each side of a region.
+pub const REGION_SIZE: i32 = 32;
+
+/// The magic bytes at the start of every region file.
+const REGION_MAGIC: &[u8; 4] = b"VXRG";
+/// The version of the region file layout.
+const REGION_VERSION: u32 = 1;
+
+/// The file holding decoration blocks that are waiting for their chunks to be generated.
+const PENDING_DECORATIONS_FILE: &str = "pending_decorations.bin";
+/// The version of the pending decorations layout.
+const PENDING_DECORATIONS_VERSION: u8 = 1;
+
+/// The number of bytes before the first chunk payload.
+const HEADER_SIZE: usize = 8 + (REGION_SIZE * REGION_SIZE) as usize * 8;
+
+/// Returns the position of the region that contains the given chunk.
+/// For example, (33, -1) -> (1, -1).
+pub fn chunk_to_region_position(chunk_pos: (i32, i32)) -> (i32, i32) {
+    (
+        chunk_pos.0.div_euclid(REGION_SIZE),
+        chunk_pos.1.div_euclid(REGION_SIZE),
+    )
+}
+
+/// Returns the index of a chunk in its region's offset table.
+fn region_index(chunk_pos: (i32, i32)) -> usize {
+    let x = chunk_pos.0.rem_euclid(REGION_SIZE);
+    let z = chunk_pos.1.rem_euclid(REGION_SIZE);
+
+    (x + z * REGION_SIZE) as usize
+}
+
+/// A single open region file, holding the compressed data of up to 32x32 chunks.
+///
+/// The file starts with the magic bytes and a version, followed by a table
+/// with an (offset, length) pair for every chunk in the region. An offset of
+/// zero means the chunk hasn't been saved. The zlib compressed chunk payloads
+/// follow the table.
+///
+/// Only the table is kept in memory. Payloads are read and written where
+/// they are in the file: a chunk that still fits in its old space is
+/// overwritten in place, and one that doesn't is appended to the end.
+#[derive(Debug)]
+struct RegionFile {
+    file: fs::File,
+    /// The (offset, length) of every chunk, indexed by `region_index`.
+    entries: Vec<(u32, u32)>,
+}
+
+impl RegionFile {
+    /// Opens a region file for reading, or returns `None` if it doesn't exist.
+    fn open(path: &Path) -> io::Result<Option<Self>> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        Self::read_header(file, path).map(Some)
+    }
+
+    /// Opens a region file for writing, creating it if it doesn't exist.
+    fn open_or_create(path: &Path) -> io::Result<Self> {
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        if file.metadata()?.len() > 0 {
+            return Self::read_header(file, path);
+        }
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(REGION_MAGIC);
+        header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+        header.resize(HEADER_SIZE, 0);
+        file.write_all(&header)?;
+
+        Ok(Self {
+            file,
+            entries: vec![(0, 0); (REGION_SIZE * REGION_SIZE) as usize],
+        })
+    }
+
+    /// Reads the magic bytes, version and chunk table at the start of a file.
+    fn read_header(mut file: fs::File, path: &Path) -> io::Result<Self> {
+        let mut header = vec![0; HEADER_SIZE];
+
+        match file.read_exact(&mut header) {
+            Ok(()) if &header[0..4] == REGION_MAGIC => {}
+            Err(err) if err.kind() != io::ErrorKind::UnexpectedEof => return Err(err),
+            _ => {
+                return Err(invalid_data(format!(
+                    "'{}' is not a region file",
+                    path.display()
+                )))
+            }
+        }
+
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        if version != REGION_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported region version {} in '{}'",
+                version,
+                path.display()
+            )));
+        }
+
+        let entries = header[8..]
+            .chunks_exact(8)
+            .map(|entry| {
+                (
+                    u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+                    u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        Ok(Self { file, entries })
+    }
+
+    /// Reads the compressed payload of a chunk, if it has been saved.
+    fn read_chunk(&mut self, index: usize) -> io::Result<Option<Vec<u8>>> {
+        let (offset, length) = self.entries[index];
+
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let mut payload = vec![0; length as usize];
+
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file
+            .read_exact(&mut payload)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => invalid_data("Truncated chunk data".to_string()),
+                _ => err,
+            })?;
+
+        Ok(Some(payload))
+    }
+
+    /// Writes the compressed payload of a chunk, and then its table entry.
+    fn write_chunk(&mut self, index: usize, payload: &[u8]) -> io::Result<()> {
+        let end = self.file.metadata()?.len().max(HEADER_SIZE as u64);
+        let (offset, _) = self.entries[index];
+
+        // A chunk owns the space up to the next payload, or the end of the file
+        let space = match offset {
+            0 => 0,
+            offset => {
+                let next = self
+                    .entries
+                    .iter()
+                    .map(|(other, _)| *other as u64)
+                    .filter(|other| *other > offset as u64)
+                    .min()
+                    .unwrap_or(end);
+
+                next - offset as u64
+            }
+        };
+
+        let offset = if payload.len() as u64 <= space {
+            offset as u64
+        } else {
+            end
+        };
+
+        let offset = u32::try_from(offset)
+            .map_err(|_| invalid_data("The region file is too large".to_string()))?;
+
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(payload)?;
+
+        self.entries[index] = (offset, payload.len() as u32);
+
+        let mut entry = [0; 8];
+        entry[0..4].copy_from_slice(&offset.to_le_bytes());
+        entry[4..8].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+
+        self.file.seek(SeekFrom::Start(8 + index as u64 * 8))?;
+        self.file.write_all(&entry)
+    }
+}
+
+/// Loads and saves chunks in the region files of a directory.
+#[derive(Debug, Clone)]
+pub struct RegionStorage {
+    /// The directory the region files are stored in.
+    directory: PathBuf,
+}
+
+impl RegionStorage {
+    /// Creates a region storage in the given directory.
+    /// The directory is created once the first chunk is saved.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the path of the file for the given region.
+    fn region_path(&self, region_pos: (i32, i32)) -> PathBuf {
+        self.directory
+            .join(format!("r.{}.{}.region", region_pos.0, region_pos.1))
+    }
+
+    /// Loads a chunk, if it has been saved before.
+    pub fn load_chunk(&self, chunk_pos: (i32, i32)) -> io::Result<Option<Chunk>> {
+        let path = self.region_path(chunk_to_region_position(chunk_pos));
+
+        let mut region = match RegionFile::open(&path)? {
+            Some(region) => region,
+            None => return Ok(None),
+        };
+
+        let compressed = match region.read_chunk(region_index(chunk_pos))? {
+            Some(compressed) => compressed,
+            None => return Ok(None),
+        };
+
+        let mut bytes = Vec::new();
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut bytes)?;
+
+        let chunk = decode_chunk(&bytes)?;
+
+        if chunk.position != chunk_pos {
+            return Err(invalid_data(format!(
+                "Expected chunk {:?}, but found chunk {:?}",
+                chunk_pos, chunk.position
+            )));
+        }
+
+        Ok(Some(chunk))
+    }
+
+    /// Saves a single chunk.
+    pub fn save_chunk(&self, chunk: &Chunk) -> io::Result<()> {
+        self.save_chunks([chunk])
+    }
+
+    /// Saves a set of chunks, opening each affected region file once.
+    pub fn save_chunks<'a>(&self, chunks: impl IntoIterator<Item = &'a Chunk>) -> io::Result<()> {
+        let mut regions = HashMap::<(i32, i32), Vec<&Chunk>>::new();
+
+        for chunk in chunks {
+            regions
+                .entry(chunk_to_region_position(chunk.position))
+                .or_default()
+                .push(chunk);
+        }
+
+        if regions.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        for (region_pos, chunks) in regions {
+            let mut region = RegionFile::open_or_create(&self.region_path(region_pos))?;
+
+            for chunk in chunks {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&encode_chunk(chunk))?;
+
+                region.write_chunk(region_index(chunk.position), &encoder.finish()?)?;
+            }
+
+            region.file.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the decoration blocks that are waiting for their chunks to be
+    /// generated, saved with `save_pending_decorations`.
+    pub fn load_pending_decorations(&self) -> io::Result<Vec<Placement>> {
+        let bytes = match fs::read(self.directory.join(PENDING_DECORATIONS_FILE)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut reader = ByteReader::new(&bytes);
+
+        let version = reader.u8()?;
+        if version != PENDING_DECORATIONS_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported pending decorations version {}",
+                version
+            )));
+        }
+
+        (0..reader.u32()?)
+            .map(|_| {
+                Ok(Placement {
+                    position: (reader.i32()?, reader.i32()?, reader.i32()?),
+                    voxel: reader.voxel()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Saves the decoration blocks that are waiting for their chunks to be
+    /// generated, replacing any that were saved before. All numbers are little
+    /// endian: a version byte and the number of blocks, followed by the
+    /// position and voxel of each block.
+    pub fn save_pending_decorations(&self, placements: &[Placement]) -> io::Result<()> {
+        let path = self.directory.join(PENDING_DECORATIONS_FILE);
+
+        if placements.is_empty() {
+            return match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+
+        let mut bytes = vec![PENDING_DECORATIONS_VERSION];
+        bytes.extend_from_slice(&(placements.len() as u32).to_le_bytes());
+
+        for placement in placements {
+            let (x, y, z) = placement.position;
+
+            bytes.extend_from_slice(&x.to_le_bytes());
+            bytes.extend_from_slice(&y.to_le_bytes());
+            bytes.extend_from_slice(&z.to_le_bytes());
+            write_voxel(&mut bytes, placement.voxel);
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        // Write to a temporary file first, so a crash can't leave half of the file
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, bytes)?;
+        fs::rename(temp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::{
+        chunk::{CHUNK_HEIGHT, CHUNK_WIDTH},
+        voxel::VoxelKind,
+    };
+
+    /// Returns an empty directory for a test, named after it.
+    fn test_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("region_test_{}_{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    /// Creates a chunk with the given number of random blocks, which doesn't
+    /// compress well.
+    fn noisy_chunk(position: (i32, i32), blocks: usize, seed: u64) -> Chunk {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut chunk = Chunk::new(position);
+
+        for _ in 0..blocks {
+            let pos = (
+                rng.gen_range(0..CHUNK_WIDTH),
+                rng.gen_range(0..CHUNK_HEIGHT),
+                rng.gen_range(0..CHUNK_WIDTH),
+            );
+
+            chunk.set(pos, VoxelKind(rng.gen_range(1..8)));
+        }
+
+        chunk
+    }
+
+    fn assert_same_blocks(a: &Chunk, b: &Chunk) {
+        assert_eq!(a.position, b.position);
+
+        for x in 0..CHUNK_WIDTH {
+            for y in 0..CHUNK_HEIGHT {
+                for z in 0..CHUNK_WIDTH {
+                    assert_eq!(a.get((x, y, z)), b.get((x, y, z)), "{:?}", (x, y, z));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_are_updated_in_place_when_they_fit() {
+        let directory = test_directory("in_place");
+        let storage = RegionStorage::new(&directory);
+        let path = storage.region_path((0, 0));
+
+        let first = noisy_chunk((0, 0), 2000, 1);
+        let second = noisy_chunk((1, 0), 2000, 2);
+        storage.save_chunks([&first, &second]).unwrap();
+
+        let size = fs::metadata(&path).unwrap().len();
+
+        // A smaller chunk reuses the space of the old one
+        let mut smaller = Chunk::new((0, 0));
+        smaller.fill(VoxelKind(1));
+        storage.save_chunk(&smaller).unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().len(), size);
+        assert_same_blocks(&storage.load_chunk((0, 0)).unwrap().unwrap(), &smaller);
+
+        // The space is kept after shrinking, so the original fits again
+        storage.save_chunk(&first).unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().len(), size);
+        assert_same_blocks(&storage.load_chunk((0, 0)).unwrap().unwrap(), &first);
+
+        // A larger chunk is appended, without touching its neighbours
+        let larger = noisy_chunk((0, 0), 8000, 3);
+        storage.save_chunk(&larger).unwrap();
+
+        assert!(fs::metadata(&path).unwrap().len() > size);
+        assert_same_blocks(&storage.load_chunk((0, 0)).unwrap().unwrap(), &larger);
+        assert_same_blocks(&storage.load_chunk((1, 0)).unwrap().unwrap(), &second);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_chunks_load_as_none() {
+        let directory = test_directory("missing");
+        let storage = RegionStorage::new(&directory);
+
+        assert!(storage.load_chunk((0, 0)).unwrap().is_none());
+
+        storage.save_chunk(&noisy_chunk((0, 0), 10, 1)).unwrap();
+
+        assert!(storage.load_chunk((0, 0)).unwrap().is_some());
+        assert!(storage.load_chunk((5, 5)).unwrap().is_none());
+        assert!(storage.load_chunk((-1, 0)).unwrap().is_none());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let directory = test_directory("invalid");
+        let storage = RegionStorage::new(&directory);
+
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(storage.region_path((0, 0)), b"not a region").unwrap();
+
+        assert!(storage.load_chunk((0, 0)).is_err());
+        assert!(storage.save_chunk(&Chunk::new((0, 0))).is_err());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use nalgebra_glm as glm;
+
+use crate::{
+    config::{Config, ConfigTable, ConfigValue},
+    systems::generators::{generator_registry, TerrainGenerator},
+};
+
+use super::{chunk_format::invalid_data, region::RegionStorage};
+
+/// The version of the world format. Bump this when the layout of a world
+/// changes, and add a migration to `WorldMetadata::migrate`.
+pub const WORLD_FORMAT_VERSION: i64 = 1;
+
+/// The name of the metadata file inside of a world directory.
+pub const METADATA_FILE: &str = "world.toml";
+/// The name of the directory holding the region files inside of a world directory.
+pub const REGION_DIRECTORY: &str = "region";
+
+/// Everything needed to reopen a world, other than its chunks.
+#[derive(Debug, Clone)]
+pub struct WorldMetadata {
+    /// The version of the format the world was saved with.
+    pub version: i64,
+
+    /// The seed used for generating terrain.
+    pub seed: u32,
+
+    /// The generator used for new chunks.
+    pub generator: Arc<dyn TerrainGenerator>,
+
+    /// The position of the camera.
+    pub camera_position: glm::Vec3,
+    /// The yaw of the camera, in radians.
+    pub camera_yaw: f32,
+    /// The pitch of the camera, in radians.
+    pub camera_pitch: f32,
+}
+
+impl WorldMetadata {
+    /// Creates the metadata for a new world.
+    pub fn new(
+        seed: u32,
+        generator: Arc<dyn TerrainGenerator>,
+        camera_position: glm::Vec3,
+    ) -> Self {
+        Self {
+            version: WORLD_FORMAT_VERSION,
+            seed,
+            generator,
+            camera_position,
+            camera_yaw: -90.0f32.to_radians(),
+            camera_pitch: 0.0,
+        }
+    }
+
+    /// Reads the metadata from a config file, migrating it to the current version.
+    pub fn from_config(mut config: Config) -> Result<Self, String> {
+        Self::migrate(&mut config)?;
+
+        let root = &config.root;
+
+        let seed = root
+            .get_integer("seed")
+            .and_then(|seed| u32::try_from(seed).ok())
+            .ok_or("Missing or invalid 'seed'")?;
+
+        let mut params = config
+            .tables
+            .get("generator")
+            .cloned()
+            .ok_or("Missing [generator] table")?;
+        let name = match params.remove("name") {
+            Some(ConfigValue::String(name)) => name,
+            _ => return Err("Missing generator 'name'".to_string()),
+        };
+        let generator = generator_registry().create(&name, seed, &params)?;
+
+        let camera = config.tables.get("camera").cloned().unwrap_or_default();
+        let camera_position = match camera.get_floats("position").as_deref() {
+            Some([x, y, z]) => glm::vec3(*x as f32, *y as f32, *z as f32),
+            Some(_) => return Err("Camera 'position' must have 3 components".to_string()),
+            None => glm::vec3(0.0, 0.0, 0.0),
+        };
+
+        Ok(Self {
+            version: WORLD_FORMAT_VERSION,
+            seed,
+            generator,
+            camera_position,
+            camera_yaw: camera.get_float("yaw").unwrap_or(-90.0f64.to_radians()) as f32,
+            camera_pitch: camera.get_float("pitch").unwrap_or(0.0) as f32,
+        })
+    }
+
+    /// Upgrades metadata saved by an older version of the engine to the current format.
+    fn migrate(config: &mut Config) -> Result<(), String> {
+        let version = config
+            .root
+            .get_integer("version")
+            .ok_or("Missing format 'version'")?;
+
+        if version > WORLD_FORMAT_VERSION {
+            return Err(format!(
+                "World was saved with format version {}, but only up to {} is supported",
+                version, WORLD_FORMAT_VERSION
+            ));
+        }
+
+        // Migrations go here, each one upgrading the config by a single version
+
+        config.root.insert("version", WORLD_FORMAT_VERSION);
+        Ok(())
+    }
+
+    /// Converts the metadata into a config file.
+    pub fn to_config(&self) -> Config {
+        let mut config = Config::default();
+
+        config.root.insert("version", self.version);
+        config.root.insert("seed", self.seed as i64);
+
+        let mut generator = self.generator.params();
+        generator.insert("name", self.generator.name());
+        config.tables.insert("generator".to_string(), generator);
+
+        let mut camera = ConfigTable::new();
+        let position = self.camera_position;
+        camera.insert("position", vec![position.x, position.y, position.z]);
+        camera.insert("yaw", self.camera_yaw);
+        camera.insert("pitch", self.camera_pitch);
+        config.tables.insert("camera".to_string(), camera);
+
+        config
+    }
+}
+
+/// A directory holding a world's metadata and region files.
+#[derive(Debug)]
+pub struct WorldDirectory {
+    /// The path of the directory.
+    pub path: PathBuf,
+
+    /// The metadata of the world.
+    pub metadata: WorldMetadata,
+}
+
+impl WorldDirectory {
+    /// Opens the world in the given directory. If it doesn't contain a world
+    /// yet, a new one is created with the metadata returned by `create`.
+    pub fn open_or_create(
+        path: impl AsRef<Path>,
+        create: impl FnOnce() -> WorldMetadata,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let metadata_path = path.join(METADATA_FILE);
+
+        if metadata_path.exists() {
+            let metadata = Config::load(&metadata_path)
+                .and_then(WorldMetadata::from_config)
+                .map_err(invalid_data)?;
+
+            return Ok(Self { path, metadata });
+        }
+
+        let world = Self {
+            path,
+            metadata: create(),
+        };
+        world.save()?;
+
+        Ok(world)
+    }
+
+    /// Writes the metadata file.
+    pub fn save(&self) -> io::Result<()> {
+        std::fs::create_dir_all(&self.path)?;
+
+        self.metadata
+            .to_config()
+            .save(self.path.join(METADATA_FILE))
+            .map_err(io::Error::other)
+    }
+
+    /// Returns the storage for the world's chunks.
+    pub fn region_storage(&self) -> RegionStorage {
+        RegionStorage::new(self.path.join(REGION_DIRECTORY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates the metadata of a world with a flat plane of dirt.
+    fn flat_world() -> WorldMetadata {
+        crate::init_test_globals();
+
+        let mut params = ConfigTable::new();
+        params.insert("block", "dirt");
+        params.insert("height", 12i64);
+
+        let generator = generator_registry()
+            .create("flat_plane", 77, &params)
+            .unwrap();
+
+        let mut metadata = WorldMetadata::new(77, generator, glm::vec3(1.5, 40.0, -3.25));
+        metadata.camera_yaw = 0.5;
+        metadata.camera_pitch = -0.25;
+
+        metadata
+    }
+
+    fn assert_same_world(a: &WorldMetadata, b: &WorldMetadata) {
+        assert_eq!(a.version, b.version);
+        assert_eq!(a.seed, b.seed);
+        assert_eq!(a.generator.name(), b.generator.name());
+        assert_eq!(a.generator.params(), b.generator.params());
+        assert_eq!(a.camera_position, b.camera_position);
+        assert_eq!(a.camera_yaw, b.camera_yaw);
+        assert_eq!(a.camera_pitch, b.camera_pitch);
+    }
+
+    #[test]
+    fn metadata_round_trips_through_world_toml() {
+        let metadata = flat_world();
+
+        let directory =
+            std::env::temp_dir().join(format!("world_meta_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let created = WorldDirectory::open_or_create(&directory, || metadata.clone()).unwrap();
+        assert_same_world(&created.metadata, &metadata);
+
+        let source = std::fs::read_to_string(directory.join(METADATA_FILE)).unwrap();
+        assert!(source.contains("[generator]"), "{}", source);
+        assert!(source.contains("name = \"flat_plane\""), "{}", source);
+
+        // Reopening reads the file instead of creating a new world
+        let opened =
+            WorldDirectory::open_or_create(&directory, || panic!("The world should already exist"))
+                .unwrap();
+        assert_same_world(&opened.metadata, &metadata);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn older_versions_are_migrated_and_newer_ones_rejected() {
+        let mut config = flat_world().to_config();
+
+        config.root.insert("version", 0i64);
+        let migrated = WorldMetadata::from_config(config.clone()).unwrap();
+        assert_eq!(migrated.version, WORLD_FORMAT_VERSION);
+
+        let mut migrated_config = config.clone();
+        WorldMetadata::migrate(&mut migrated_config).unwrap();
+        assert_eq!(
+            migrated_config.root.get_integer("version"),
+            Some(WORLD_FORMAT_VERSION)
+        );
+
+        config.root.insert("version", WORLD_FORMAT_VERSION + 1);
+        let err = WorldMetadata::from_config(config.clone()).unwrap_err();
+        assert!(err.contains("only up to"), "{}", err);
+
+        config.root.remove("version");
+        assert!(WorldMetadata::from_config(config).is_err());
+    }
+
+    #[test]
+    fn unknown_generator_params_are_rejected() {
+        let mut config = flat_world().to_config();
+        config
+            .tables
+            .get_mut("generator")
+            .unwrap()
+            .insert("hieght", 20i64);
+
+        let err = WorldMetadata::from_config(config).unwrap_err();
+        assert_eq!(
+            err,
+            "Unknown parameter 'hieght' for the 'flat_plane' generator"
+        );
+    }
+}
@@ -1,12 +1,26 @@
-use log::info;
+use log::{error, info};
 use nalgebra_glm as glm;
 
-use crate::{chunk::Chunk, utils::world_to_chunk_position};
+use std::{collections::HashMap, sync::Arc};
 
-use super::chunk_builder::ChunkGenStrategy;
+use crate::{
+    chunk::Chunk,
+    registry::block_registry,
+    rendering::mesh::{Mesh, MeshBuilder, MeshingMode},
+    storage::region::RegionStorage,
+    utils::world_to_chunk_position,
+    world::World,
+};
+
+use super::generators::{
+    decorations::{place_in_chunk, place_in_world, Placement},
+    GenContext, TerrainGenerator,
+};
 
 pub const CHUNK_LOAD_DISTANCE: i32 = 4;
 pub const CHUNKS_TO_BUILT_PER_TICK: usize = 1;
+/// The number of chunk meshes built and uploaded to the GPU each frame.
+pub const MESH_UPLOADS_PER_FRAME: usize = 2;
 
 /// If the chunk is currently loaded or not
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,46 +33,82 @@ pub enum ChunkState {
 /// Automatically loads and unloads chunks as the player moves.
 pub struct ChunkManager {
     /// All chunks that have been built, regardless of whether they are loaded or not.
-    pub chunks: Vec<(Chunk, ChunkState)>,
+    pub world: World,
+
+    /// Whether each built chunk is currently loaded.
+    pub chunk_states: HashMap<(i32, i32), ChunkState>,
 
     /// The current chunk that the player is in.
     pub current_chunk: (i32, i32),
 
-    /// The strategy to use for generating chunks.
-    pub gen_strategy: ChunkGenStrategy,
+    /// The generator used for new chunks.
+    pub generator: Arc<dyn TerrainGenerator>,
 
     /// The queue of chunks that still need to be built.
     pub chunk_queue: Vec<(i32, i32)>,
+
+    /// Where chunks are saved to, and loaded from.
+    pub storage: RegionStorage,
+
+    /// How the meshes of chunks are built.
+    pub meshing_mode: MeshingMode,
+
+    /// Decoration blocks that landed in chunks that haven't been generated yet,
+    /// keyed by chunk position. They are placed once their chunk is generated.
+    pub pending_decorations: HashMap<(i32, i32), Vec<Placement>>,
 }
 
 impl ChunkManager {
-    /// Creates a new chunk manager.
-    pub fn new(gen_strategy: ChunkGenStrategy, player_pos: glm::Vec3) -> Self {
+    /// Creates a new chunk manager, and queues the chunks around the player.
+    pub fn new(
+        generator: Arc<dyn TerrainGenerator>,
+        storage: RegionStorage,
+        player_pos: glm::Vec3,
+    ) -> Self {
         let player_x = player_pos.x;
         let player_z = player_pos.z;
 
         let chunk_pos = world_to_chunk_position(player_x as i32, player_z as i32);
 
-        Self {
-            chunks: Vec::new(),
+        let mut pending_decorations = HashMap::<_, Vec<_>>::new();
+
+        match storage.load_pending_decorations() {
+            Ok(placements) => {
+                for placement in placements {
+                    pending_decorations
+                        .entry(placement.chunk_position())
+                        .or_default()
+                        .push(placement);
+                }
+            }
+            Err(err) => error!("Failed to load pending decorations: {}", err),
+        }
+
+        let mut manager = Self {
+            world: World::new(),
+            chunk_states: HashMap::new(),
             current_chunk: chunk_pos,
-            gen_strategy,
+            generator,
             chunk_queue: Vec::new(),
-        }
+            storage,
+            meshing_mode: MeshingMode::Greedy,
+            pending_decorations,
+        };
+
+        // Start building the chunks around the player straight away
+        manager.add_chunks_to_queue();
+
+        manager
     }
 
     /// Adds all of the chunks that need to be loaded to the queue.
     /// If a chunk is already loaded, it will not be added to the queue.
+    /// Chunks that have been unloaded are queued again, to be reloaded.
     fn add_chunks_to_queue(&mut self) {
         let chunks_to_load = self.get_chunks_around(self.current_chunk);
         let chunks_to_load = chunks_to_load
             .iter()
-            .filter(|(cx, cz)| {
-                !self
-                    .chunks
-                    .iter()
-                    .any(|(chunk, _)| chunk.position == (*cx, *cz))
-            })
+            .filter(|pos| self.chunk_states.get(pos) != Some(&ChunkState::Loaded))
             .filter(|(cx, cz)| !self.chunk_queue.contains(&(*cx, *cz)))
             .collect::<Vec<_>>();
 
@@ -68,37 +118,165 @@ impl ChunkManager {
     }
 
     /// Unloads all chunks that are too far away from the player.
+    /// Chunks that have been modified are written back to the storage.
     fn unload_distant_chunks(&mut self) {
-        self.chunks.iter_mut().for_each(|(chunk, state)| {
+        let mut unloaded = Vec::new();
+
+        for chunk in self.world.chunks_mut() {
             let (cx, cz) = chunk.position;
             let (px, pz) = self.current_chunk;
 
             let dx = (cx - px).abs();
             let dz = (cz - pz).abs();
 
-            if dx > CHUNK_LOAD_DISTANCE || dz > CHUNK_LOAD_DISTANCE {
+            let too_far = dx > CHUNK_LOAD_DISTANCE || dz > CHUNK_LOAD_DISTANCE;
+            let state = self.chunk_states.get_mut(&chunk.position).unwrap();
+
+            if too_far && *state == ChunkState::Loaded {
+                // Unloaded chunks are kept around, so store them as compactly as possible
+                chunk.optimize();
                 *state = ChunkState::Unloaded;
+
+                if let Some(mut mesh) = chunk.mesh.take() {
+                    mesh.release();
+                }
+
+                if chunk.modified {
+                    unloaded.push(chunk.position);
+                }
+            }
+        }
+
+        self.save_chunks(&unloaded);
+    }
+
+    /// Saves the given chunks to the storage, if they have been built.
+    fn save_chunks(&mut self, positions: &[(i32, i32)]) {
+        let chunks = positions
+            .iter()
+            .filter_map(|pos| self.world.get_chunk(*pos))
+            .collect::<Vec<_>>();
+
+        if chunks.is_empty() {
+            return;
+        }
+
+        if let Err(err) = self.storage.save_chunks(chunks) {
+            error!("Failed to save chunks: {}", err);
+            return;
+        }
+
+        for pos in positions {
+            if let Some(chunk) = self.world.get_chunk_mut(*pos) {
+                chunk.modified = false;
             }
-        });
+        }
+
+        info!("Saved {} chunks", positions.len());
+    }
+
+    /// Saves every chunk that has been modified since it was last saved, and
+    /// the decorations still waiting for their chunks to be generated.
+    pub fn save_all(&mut self) {
+        let modified = self
+            .world
+            .chunks()
+            .filter(|chunk| chunk.modified)
+            .map(|chunk| chunk.position)
+            .collect::<Vec<_>>();
+
+        self.save_chunks(&modified);
+
+        let pending = self
+            .pending_decorations
+            .values()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+
+        if let Err(err) = self.storage.save_pending_decorations(&pending) {
+            error!("Failed to save pending decorations: {}", err);
+        }
+    }
+
+    /// Places decoration blocks that landed outside of the chunk they were
+    /// generated in. Chunks saved in an earlier session are loaded so the
+    /// blocks can be placed into them, and blocks for chunks that haven't been
+    /// generated yet wait until they are.
+    fn place_decorations(&mut self, placements: Vec<Placement>) {
+        for placement in placements.iter() {
+            let pos = placement.chunk_position();
+
+            if self.world.contains_chunk(pos) {
+                continue;
+            }
+
+            match self.storage.load_chunk(pos) {
+                Ok(Some(chunk)) => {
+                    self.world.insert(chunk);
+                    self.chunk_states.insert(pos, ChunkState::Unloaded);
+                }
+                Ok(None) => {}
+                Err(err) => error!("Failed to load chunk {:?}: {}", pos, err),
+            }
+        }
+
+        for placement in place_in_world(&mut self.world, placements) {
+            self.pending_decorations
+                .entry(placement.chunk_position())
+                .or_default()
+                .push(placement);
+        }
     }
 
     /// Loads the first n chunks per tick. This is to prevent lag spikes.
     fn build_next_chunks(&mut self) {
         for _ in 0..CHUNKS_TO_BUILT_PER_TICK {
             if let Some((cx, cz)) = self.chunk_queue.pop() {
-                // Check if the chunk is already loaded
-                if let Some((_, status)) = self
-                    .chunks
-                    .iter_mut()
-                    .find(|(chunk, _)| chunk.position == (cx, cz))
-                {
-                    *status = ChunkState::Loaded;
+                // Check if the chunk has already been built
+                if let Some(state) = self.chunk_states.get_mut(&(cx, cz)) {
+                    *state = ChunkState::Loaded;
                     continue;
                 }
 
-                let mut chunk = Chunk::new((cx, cz));
-                self.gen_strategy.apply(&mut chunk);
-                self.chunks.push((chunk, ChunkState::Loaded));
+                let mut decorations = Vec::new();
+
+                let mut chunk = match self.storage.load_chunk((cx, cz)) {
+                    Ok(Some(chunk)) => chunk,
+                    result => {
+                        if let Err(err) = result {
+                            error!(
+                                "Failed to load chunk {:?}, regenerating it: {}",
+                                (cx, cz),
+                                err
+                            );
+                        }
+
+                        let mut chunk = Chunk::new((cx, cz));
+                        let ctx = GenContext::new(block_registry());
+                        self.generator.generate(&mut chunk, &ctx);
+                        decorations = self.generator.decorate(&mut chunk, &ctx);
+
+                        chunk
+                    }
+                };
+
+                // Decorations from neighbouring chunks that were generated first.
+                // The chunk is saved, since it can't be regenerated with them
+                if let Some(placements) = self.pending_decorations.remove(&(cx, cz)) {
+                    for placement in placements.iter() {
+                        place_in_chunk(&mut chunk, placement);
+                    }
+
+                    chunk.modified = true;
+                }
+
+                chunk.optimize();
+
+                self.world.insert(chunk);
+                self.chunk_states.insert((cx, cz), ChunkState::Loaded);
+
+                self.place_decorations(decorations);
             }
         }
     }
@@ -119,6 +297,60 @@ impl ChunkManager {
         chunks
     }
 
+    /// Returns true if the chunk and the eight chunks around it have been built,
+    /// so that its mesh can see the blocks across its borders.
+    fn has_neighbours(&self, (cx, cz): (i32, i32)) -> bool {
+        (-1..=1).all(|dx| (-1..=1).all(|dz| self.world.contains_chunk((cx + dx, cz + dz))))
+    }
+
+    /// Builds and uploads the meshes of loaded chunks that don't have an up to
+    /// date one, closest to the player first, up to `MESH_UPLOADS_PER_FRAME`
+    /// each frame. Requires a GL context.
+    pub fn update_meshes(&mut self) {
+        let (px, pz) = self.current_chunk;
+
+        let mut outdated = self
+            .world
+            .chunks()
+            .filter(|chunk| self.chunk_states.get(&chunk.position) == Some(&ChunkState::Loaded))
+            .filter(|chunk| chunk.mesh.is_none() || chunk.needs_remesh())
+            .map(|chunk| chunk.position)
+            .filter(|pos| self.has_neighbours(*pos))
+            .collect::<Vec<_>>();
+
+        outdated.sort_by_key(|(cx, cz)| (cx - px).pow(2) + (cz - pz).pow(2));
+
+        for pos in outdated.into_iter().take(MESH_UPLOADS_PER_FRAME) {
+            let chunk = self.world.get_chunk(pos).unwrap();
+            let mut mesh =
+                MeshBuilder::with_mode(self.meshing_mode).build_chunk(chunk, &self.world);
+
+            if !mesh.indices.is_empty() {
+                mesh.upload();
+            }
+
+            let chunk = self.world.get_chunk_mut(pos).unwrap();
+            if let Some(mut old) = chunk.mesh.replace(mesh) {
+                old.release();
+            }
+
+            chunk.mark_clean();
+        }
+    }
+
+    /// Iterates over the meshes of every loaded chunk.
+    pub fn meshes(&self) -> impl Iterator<Item = &Mesh> {
+        self.world
+            .chunks()
+            .filter(|chunk| self.chunk_states.get(&chunk.position) == Some(&ChunkState::Loaded))
+            .filter_map(|chunk| chunk.mesh.as_ref())
+    }
+
+    /// Returns the approximate number of bytes used by all built chunks.
+    pub fn memory_usage(&self) -> usize {
+        self.world.memory_usage()
+    }
+
     /// Adds all chunks that need to be loaded to the queue.
     pub fn update(&mut self, player_pos: glm::Vec3) {
         // Build chunks in the queue
@@ -136,6 +368,12 @@ impl ChunkManager {
 
             self.add_chunks_to_queue();
             self.unload_distant_chunks();
+
+            info!(
+                "Chunk memory usage: {} KiB across {} chunks",
+                self.memory_usage() / 1024,
+                self.world.len()
+            );
         }
     }
 }
@@ -0,0 +1,151 @@
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_HEIGHT},
+    config::{ConfigTable, ConfigValue},
+    registry::block_registry,
+    systems::generators::{integer_triple, GenContext, TerrainGenerator},
+    voxel::VoxelKind,
+};
+
+/// Generates chunks with all air.
+#[derive(Debug, Clone, Copy)]
+pub struct EmptyGenerator;
+
+impl TerrainGenerator for EmptyGenerator {
+    fn name(&self) -> &'static str {
+        "empty"
+    }
+
+    fn generate(&self, chunk: &mut Chunk, _ctx: &GenContext) {
+        chunk.fill(VoxelKind::AIR);
+    }
+}
+
+/// Places a flat plane of some block below a certain height.
+#[derive(Debug, Clone, Copy)]
+pub struct FlatPlaneGenerator {
+    /// The block the plane is made of.
+    pub kind: VoxelKind,
+    /// The height of the top of the plane.
+    pub height: u32,
+}
+
+impl FlatPlaneGenerator {
+    /// Creates the generator from its `block` and `height` parameters.
+    pub fn from_params(params: &ConfigTable) -> Result<Self, String> {
+        let block = params.get_str("block").ok_or("Missing 'block' parameter")?;
+        let kind = block_registry()
+            .by_name(block)
+            .ok_or_else(|| format!("Unknown block '{}'", block))?;
+
+        let height = params
+            .get_integer("height")
+            .and_then(|height| u32::try_from(height).ok())
+            .ok_or("Missing or invalid 'height' parameter")?;
+
+        Ok(Self { kind, height })
+    }
+}
+
+impl TerrainGenerator for FlatPlaneGenerator {
+    fn name(&self) -> &'static str {
+        "flat_plane"
+    }
+
+    fn params(&self) -> ConfigTable {
+        let mut params = ConfigTable::new();
+
+        let block = block_registry()
+            .get(self.kind)
+            .map_or("air", |block| &block.name);
+
+        params.insert("block", block);
+        params.insert("height", self.height as i64);
+
+        params
+    }
+
+    fn generate(&self, chunk: &mut Chunk, _ctx: &GenContext) {
+        let top = (self.height as usize).min(CHUNK_HEIGHT - 1);
+
+        for (index, section) in chunk.sections.iter_mut().enumerate() {
+            let bottom = index * SECTION_HEIGHT;
+
+            // Sections that are entirely below the plane don't need any storage
+            if bottom + SECTION_HEIGHT - 1 <= top {
+                section.fill(self.kind);
+                continue;
+            }
+
+            if bottom > top {
+                continue;
+            }
+
+            for x in 0..CHUNK_WIDTH {
+                for z in 0..CHUNK_WIDTH {
+                    for y in 0..=(top - bottom) {
+                        section.set((x, y, z), self.kind);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Places grass at a series of positions in every chunk (used for testing).
+#[derive(Debug, Clone)]
+pub struct SingleVoxelsGenerator {
+    /// The positions of the voxels, relative to each chunk.
+    pub voxels: Vec<(usize, usize, usize)>,
+}
+
+impl SingleVoxelsGenerator {
+    /// Creates the generator from its `voxels` parameter.
+    pub fn from_params(params: &ConfigTable) -> Result<Self, String> {
+        let voxels = params
+            .get("voxels")
+            .and_then(ConfigValue::as_array)
+            .ok_or("Missing 'voxels' parameter")?;
+
+        let voxels = voxels
+            .iter()
+            .map(|voxel| {
+                let position = integer_triple(voxel).unwrap_or([-1; 3]);
+                let [x, y, z] = position.map(|c| usize::try_from(c).ok());
+
+                match (x, y, z) {
+                    (Some(x), Some(y), Some(z)) if Chunk::in_bounds((x, y, z)) => Ok((x, y, z)),
+                    _ => Err("Voxels must be [x, y, z] positions inside a chunk".to_string()),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { voxels })
+    }
+}
+
+impl TerrainGenerator for SingleVoxelsGenerator {
+    fn name(&self) -> &'static str {
+        "single_voxels"
+    }
+
+    fn params(&self) -> ConfigTable {
+        let mut params = ConfigTable::new();
+
+        let voxels = self
+            .voxels
+            .iter()
+            .map(|(x, y, z)| vec![*x as i64, *y as i64, *z as i64])
+            .collect::<Vec<_>>();
+
+        params.insert("voxels", voxels);
+        params
+    }
+
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext) {
+        let grass = ctx.block("grass");
+
+        for (x, y, z) in self.voxels.iter() {
+            chunk.set((*x, *y, *z), grass);
+        }
+    }
+}
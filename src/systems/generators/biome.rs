@@ -0,0 +1,541 @@
+#![allow(dead_code)]
+
+use std::{collections::HashMap, sync::Arc};
+
+use rand::Rng;
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::{Config, ConfigTable},
+    registry::block_registry,
+    systems::generators::{
+        caves::CaveCarver,
+        column_random,
+        decorations::{load_decorations, place_in_chunk, Decoration, Placement},
+        features::{chunk_rng, load_ores, place_features, Feature, OreVein, ORES_PATH},
+        fractal::{FractalNoise, FractalSettings, HeightCurve},
+        water::{flood, sea_level_param, DEFAULT_SEA_LEVEL},
+        ColumnLayers, GenContext, TerrainGenerator, DIRT_DEPTH,
+    },
+    voxel::VoxelKind,
+};
+
+/// The default location of the biome definitions.
+pub const BIOMES_PATH: &str = "./assets/biomes.toml";
+
+/// How quickly the influence of a biome falls off as the climate moves away
+/// from it. Higher values give narrower borders.
+const BLEND_SHARPNESS: f64 = 12.0;
+
+/// How the temperature, humidity and continentalness noise is layered.
+/// Climates change over much larger distances than the terrain.
+const CLIMATE_SETTINGS: FractalSettings = FractalSettings {
+    scale: 0.0015,
+    octaves: 3,
+    lacunarity: 2.0,
+    persistence: 0.5,
+    ridged: false,
+};
+
+/// How much the climate noise is stretched, since fractal noise rarely gets
+/// close to -1 or 1.
+const CLIMATE_CONTRAST: f64 = 2.0;
+
+/// Added to the world's seed for each climate value, so they don't line up.
+const CLIMATE_SEEDS: [u32; 3] = [0x1000_0001, 0x2000_0002, 0x3000_0003];
+
+/// Added to the world's seed when deciding where vegetation grows.
+const VEGETATION_SEED: u32 = 0x4000_0004;
+
+/// Added to the world's seed when placing features.
+const FEATURE_SEED: u32 = 0x9000_0009;
+
+/// Added to the world's seed when placing decorations.
+const DECORATION_SEED: u32 = 0xa000_000a;
+
+/// The climate of a column, with each value from -1 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Climate {
+    pub temperature: f64,
+    pub humidity: f64,
+    /// How far inland the column is, from the deep ocean to the mountains.
+    pub continentalness: f64,
+}
+
+impl Climate {
+    /// Returns how different two climates are.
+    pub fn distance(&self, other: &Climate) -> f64 {
+        let temperature = self.temperature - other.temperature;
+        let humidity = self.humidity - other.humidity;
+        let continentalness = self.continentalness - other.continentalness;
+
+        (temperature * temperature + humidity * humidity + continentalness * continentalness).sqrt()
+    }
+}
+
+/// Describes the terrain of a single biome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Biome {
+    /// The unique name of the biome.
+    pub name: String,
+
+    /// The climate where the biome is found.
+    pub climate: Climate,
+
+    /// The blocks each column is made of.
+    pub layers: ColumnLayers,
+
+    /// Maps the terrain noise of a column to its height.
+    pub height: HeightCurve,
+
+    /// The plants placed on top of the surface, with the chance of each one
+    /// growing in a column.
+    pub vegetation: Vec<(VoxelKind, f64)>,
+
+    /// The decorations placed on the surface, with the chance of each one
+    /// being placed on a column.
+    pub decorations: Vec<(Decoration, f64)>,
+}
+
+impl Biome {
+    /// Parses a biome from a `[[biome]]` table, looking up its decorations by name.
+    fn from_table(
+        table: &ConfigTable,
+        decorations: &HashMap<String, Decoration>,
+    ) -> Result<Self, String> {
+        let name = table
+            .get_str("name")
+            .ok_or("Biome is missing a 'name'")?
+            .to_string();
+
+        let with_name = |err: String| format!("Biome '{}': {}", name, err);
+
+        let block = |key: &str, default: &str| {
+            let block = table.get_str(key).unwrap_or(default);
+
+            block_registry()
+                .by_name(block)
+                .ok_or_else(|| with_name(format!("Unknown block '{}'", block)))
+        };
+
+        let float = |key: &str| match table.get(key) {
+            Some(value) => value
+                .as_float()
+                .ok_or_else(|| with_name(format!("'{}' must be a number", key))),
+            None => Ok(0.0),
+        };
+
+        let climate = Climate {
+            temperature: float("temperature")?,
+            humidity: float("humidity")?,
+            continentalness: float("continentalness")?,
+        };
+
+        let filler_depth = match table.get("filler_depth") {
+            Some(depth) => depth
+                .as_integer()
+                .and_then(|depth| usize::try_from(depth).ok())
+                .ok_or_else(|| with_name("'filler_depth' can't be negative".to_string()))?,
+            None => DIRT_DEPTH,
+        };
+
+        let layers = ColumnLayers {
+            surface: block("surface", "grass")?,
+            filler: block("filler", "dirt")?,
+            filler_depth,
+            base: block("base", "stone")?,
+        };
+
+        let height = match table.get("height") {
+            Some(height) => HeightCurve::from_value(height).map_err(with_name)?,
+            None => HeightCurve::new(vec![(-1.0, 40.0), (1.0, 48.0)])?,
+        };
+
+        let vegetation = chance_pairs(table, "vegetation")
+            .map_err(with_name)?
+            .into_iter()
+            .map(|(block, chance)| {
+                block_registry()
+                    .by_name(block)
+                    .map(|block| (block, chance))
+                    .ok_or_else(|| with_name(format!("Unknown block '{}'", block)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let decorations = chance_pairs(table, "decorations")
+            .map_err(with_name)?
+            .into_iter()
+            .map(|(decoration, chance)| {
+                decorations
+                    .get(decoration)
+                    .map(|found| (found.clone(), chance))
+                    .ok_or_else(|| with_name(format!("Unknown decoration '{}'", decoration)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            name,
+            climate,
+            layers,
+            height,
+            vegetation,
+            decorations,
+        })
+    }
+}
+
+/// Reads an optional array of `[name, chance]` pairs.
+fn chance_pairs<'a>(table: &'a ConfigTable, key: &str) -> Result<Vec<(&'a str, f64)>, String> {
+    table
+        .get(key)
+        .and_then(|value| value.as_array())
+        .unwrap_or_default()
+        .iter()
+        .map(|pair| match pair.as_array() {
+            Some([name, chance]) => Some((name.as_str()?, chance.as_float()?)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| format!("Each entry of '{}' must be a [name, chance] pair", key))
+}
+
+/// Reads the biome definitions from the `[[biome]]` tables of a config, along
+/// with the `[[decoration]]` tables they refer to.
+pub fn load_biomes(config: &Config) -> Result<Vec<Biome>, String> {
+    let decorations = load_decorations(config)?;
+    let mut biomes = Vec::<Biome>::new();
+
+    for table in config.arrays.get("biome").into_iter().flatten() {
+        let biome = Biome::from_table(table, &decorations)?;
+
+        if biomes.iter().any(|other| other.name == biome.name) {
+            return Err(format!("Duplicate biome name '{}'", biome.name));
+        }
+
+        biomes.push(biome);
+    }
+
+    if biomes.is_empty() {
+        return Err("At least one biome must be defined".to_string());
+    }
+
+    Ok(biomes)
+}
+
+/// The biome and height chosen for a column.
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeColumn<'a> {
+    /// The biome whose climate is closest to the column's.
+    pub biome: &'a Biome,
+    /// The climate of the column.
+    pub climate: Climate,
+    /// The number of blocks in the column, blended between nearby biomes.
+    pub height: usize,
+}
+
+/// Picks a biome for each column from its climate, and shapes the terrain
+/// with that biome's blocks and height curve.
+///
+/// Every biome maps the same terrain noise to a height, and a column's height
+/// is the average of them, weighted by how close each biome's climate is to
+/// the column's. Since climates change smoothly, so do heights at borders.
+#[derive(Debug, Clone)]
+pub struct BiomeGenerator {
+    /// The path of the biome definitions.
+    pub path: String,
+
+    /// The biomes that can be chosen.
+    pub biomes: Arc<Vec<Biome>>,
+
+    /// The seed of the world.
+    seed: u32,
+
+    /// The noise shared by the height curves of every biome.
+    terrain: FractalNoise,
+
+    /// The temperature, humidity and continentalness noise.
+    climate: [FractalNoise; 3],
+
+    /// Carves caves below the surface, if they are enabled.
+    caves: Option<CaveCarver>,
+
+    /// Columns lower than this are covered with water. Zero means no water.
+    pub sea_level: usize,
+
+    /// The path of the ore definitions, if ores are placed.
+    ores_path: Option<String>,
+
+    /// The features placed after caves are carved.
+    features: Arc<Vec<Box<dyn Feature>>>,
+}
+
+impl BiomeGenerator {
+    /// Creates the generator with the given biomes.
+    pub fn new(
+        seed: u32,
+        path: &str,
+        biomes: Vec<Biome>,
+        terrain: FractalSettings,
+        caves: bool,
+    ) -> Self {
+        Self {
+            path: path.to_string(),
+            biomes: Arc::new(biomes),
+            seed,
+            terrain: FractalNoise::new(seed, terrain),
+            climate: CLIMATE_SEEDS
+                .map(|offset| FractalNoise::new(seed.wrapping_add(offset), CLIMATE_SETTINGS)),
+            caves: caves.then(|| CaveCarver::new(seed)),
+            sea_level: DEFAULT_SEA_LEVEL,
+            ores_path: None,
+            features: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Places the given ore veins in every chunk, replacing any other features.
+    pub fn with_ores(mut self, path: &str, ores: Vec<OreVein>) -> Self {
+        self.ores_path = Some(path.to_string());
+        self.features = Arc::new(
+            ores.into_iter()
+                .map(|ore| Box::new(ore) as Box<dyn Feature>)
+                .collect(),
+        );
+
+        self
+    }
+
+    /// Creates the generator from its optional `path`, `caves`, `sea_level` and
+    /// `ores` parameters, and the parameters of `FractalSettings` for the
+    /// terrain noise. Caves are enabled by default, and `ores` is the path of
+    /// the ore definitions, or an empty string for no ores.
+    pub fn from_params(seed: u32, params: &ConfigTable) -> Result<Self, String> {
+        let path = params.get_str("path").unwrap_or(BIOMES_PATH);
+
+        let caves = match params.get("caves") {
+            Some(caves) => caves.as_bool().ok_or("'caves' must be a boolean")?,
+            None => true,
+        };
+
+        let biomes = load_biomes(&Config::load(path)?)
+            .map_err(|err| format!("Failed to load biomes from '{}': {}", path, err))?;
+
+        let mut generator = Self::new(
+            seed,
+            path,
+            biomes,
+            FractalSettings::from_params(params)?,
+            caves,
+        );
+        generator.sea_level = sea_level_param(params)?;
+
+        let ores_path = params.get_str("ores").unwrap_or(ORES_PATH);
+        if ores_path.is_empty() {
+            return Ok(generator);
+        }
+
+        let ores = load_ores(&Config::load(ores_path)?)
+            .map_err(|err| format!("Failed to load ores from '{}': {}", ores_path, err))?;
+
+        Ok(generator.with_ores(ores_path, ores))
+    }
+
+    /// Returns the climate of the column at the given world position.
+    pub fn climate(&self, x: i32, z: i32) -> Climate {
+        let [temperature, humidity, continentalness] = self
+            .climate
+            .each_ref()
+            .map(|noise| (noise.sample(x as f64, z as f64) * CLIMATE_CONTRAST).clamp(-1.0, 1.0));
+
+        Climate {
+            temperature,
+            humidity,
+            continentalness,
+        }
+    }
+
+    /// Returns the biome and height of the column at the given world position.
+    pub fn column(&self, x: i32, z: i32) -> BiomeColumn<'_> {
+        let climate = self.climate(x, z);
+
+        let (biome, nearest) = self
+            .biomes
+            .iter()
+            .map(|biome| (biome, biome.climate.distance(&climate)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("There is always at least one biome");
+
+        let noise = self.terrain.sample(x as f64, z as f64);
+
+        let mut height = 0.0;
+        let mut total_weight = 0.0;
+
+        for other in self.biomes.iter() {
+            let distance = other.climate.distance(&climate);
+            let weight = (-(distance - nearest) * BLEND_SHARPNESS).exp();
+
+            height += other.height.evaluate(noise) * weight;
+            total_weight += weight;
+        }
+
+        let height = (height / total_weight)
+            .round()
+            .clamp(0.0, CHUNK_HEIGHT as f64) as usize;
+
+        BiomeColumn {
+            biome,
+            climate,
+            height,
+        }
+    }
+}
+
+impl TerrainGenerator for BiomeGenerator {
+    fn name(&self) -> &'static str {
+        "biomes"
+    }
+
+    fn params(&self) -> ConfigTable {
+        let mut params = ConfigTable::new();
+
+        params.insert("path", self.path.as_str());
+        params.insert("caves", self.caves.is_some());
+        params.insert("sea_level", self.sea_level as i64);
+        params.insert("ores", self.ores_path.as_deref().unwrap_or(""));
+        self.terrain.settings.write_params(&mut params);
+
+        params
+    }
+
+    /// Fills each column with its biome's blocks, places the bedrock floor,
+    /// carves caves, places ores, floods everything below sea level, and then
+    /// grows vegetation on the remaining surface.
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext) {
+        let mut columns = Vec::with_capacity(CHUNK_WIDTH * CHUNK_WIDTH);
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+                let column = self.column(wx, wz);
+
+                column.biome.layers.fill(chunk, (x, z), 0..column.height);
+                columns.push(column);
+            }
+        }
+
+        if let Some(caves) = &self.caves {
+            caves.place_bedrock(chunk, ctx.block("bedrock"));
+            caves.carve(chunk, |x, z| columns[x * CHUNK_WIDTH + z].height);
+        }
+
+        place_features(chunk, self.seed.wrapping_add(FEATURE_SEED), &self.features);
+
+        let (origin_x, _, origin_z) = chunk.world_position((0, 0, 0));
+        let width = 0..CHUNK_WIDTH as i32;
+
+        flood(chunk, ctx, self.sea_level, |x, z| {
+            let (x, z) = (x - origin_x, z - origin_z);
+
+            if width.contains(&x) && width.contains(&z) {
+                columns[x as usize * CHUNK_WIDTH + z as usize].height
+            } else {
+                self.column(x + origin_x, z + origin_z).height
+            }
+        });
+
+        for (index, column) in columns.into_iter().enumerate() {
+            let (x, z) = (index / CHUNK_WIDTH, index % CHUNK_WIDTH);
+
+            if column.height < self.sea_level.max(1) || column.height >= CHUNK_HEIGHT {
+                continue;
+            }
+
+            // Plants can't grow where a cave has broken through the surface
+            let has_surface = chunk
+                .get((x, column.height - 1, z))
+                .is_some_and(|voxel| voxel.kind == column.biome.layers.surface);
+            if !has_surface {
+                continue;
+            }
+
+            // At most one plant grows in each column
+            let (wx, _, wz) = chunk.world_position((x, 0, z));
+            let roll = column_random(self.seed.wrapping_add(VEGETATION_SEED), wx, wz);
+            let mut chance = 0.0;
+
+            for (plant, plant_chance) in column.biome.vegetation.iter() {
+                chance += plant_chance;
+
+                if roll < chance {
+                    chunk.set((x, column.height, z), *plant);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Rolls for a decoration on each column with the biome's surface block,
+    /// in order, using a random number generator seeded by the chunk.
+    fn decorate(&self, chunk: &mut Chunk, _ctx: &GenContext) -> Vec<Placement> {
+        let mut rng = chunk_rng(self.seed.wrapping_add(DECORATION_SEED), chunk.position, 0);
+        let mut placements = Vec::new();
+
+        // Every column rolls, so that the rolls stay in sync, but columns only
+        // need their biome looked up if the roll could place something
+        let max_chance = self
+            .biomes
+            .iter()
+            .map(|biome| biome.decorations.iter().map(|(_, chance)| chance).sum())
+            .fold(0.0, f64::max);
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let roll = rng.gen::<f64>();
+                if roll >= max_chance {
+                    continue;
+                }
+
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+                let column = self.column(wx, wz);
+
+                if column.height < self.sea_level.max(1) || column.height >= CHUNK_HEIGHT {
+                    continue;
+                }
+
+                let has_surface = chunk
+                    .get((x, column.height - 1, z))
+                    .is_some_and(|voxel| voxel.kind == column.biome.layers.surface);
+                if !has_surface {
+                    continue;
+                }
+
+                let mut chance = 0.0;
+
+                for (decoration, decoration_chance) in column.biome.decorations.iter() {
+                    chance += decoration_chance;
+
+                    if roll < chance {
+                        if decoration.fits((x, z)) {
+                            let origin = (wx, column.height as i32, wz);
+                            decoration.place(origin, &mut rng, &mut placements);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        let (inside, outside): (Vec<_>, Vec<_>) = placements
+            .into_iter()
+            .partition(|placement| placement.chunk_position() == chunk.position);
+
+        for placement in inside.iter() {
+            place_in_chunk(chunk, placement);
+        }
+
+        outside
+    }
+
+    fn biome_at(&self, x: i32, z: i32) -> Option<&Biome> {
+        Some(self.column(x, z).biome)
+    }
+}
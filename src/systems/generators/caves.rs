@@ -0,0 +1,237 @@
+use noise::{NoiseFn, Perlin};
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    systems::generators::column_random,
+    voxel::VoxelKind,
+};
+
+/// The number of layers at the bottom of the world that can contain bedrock.
+/// The lowest layer is always bedrock, and each layer above it has less.
+pub const BEDROCK_LAYERS: usize = 4;
+
+/// Caves are never carved below this height, so the bedrock floor stays intact.
+pub const CAVE_FLOOR: usize = BEDROCK_LAYERS;
+
+/// The frequency of the noise that tunnels follow, in cycles per block.
+const TUNNEL_SCALE: f64 = 0.015;
+/// How much faster the tunnel noise changes vertically, which flattens tunnels.
+const TUNNEL_SQUASH: f64 = 2.0;
+/// How close to zero both tunnel noises must be for a block to be carved.
+/// Larger values give wider tunnels.
+const TUNNEL_RADIUS: f64 = 0.06;
+
+/// The frequency of the noise that caverns are carved from, in cycles per block.
+const CAVERN_SCALE: f64 = 0.02;
+/// The noise value above which caverns are carved at the bottom of the world.
+const CAVERN_THRESHOLD: f64 = 0.4;
+/// Caverns are only carved below this height, and get smaller as they near it.
+const CAVERN_CEILING: usize = 56;
+/// How much the cavern threshold rises between the bottom of the world and the
+/// cavern ceiling, and between `SURFACE_FADE` blocks deep and the surface.
+const CAVERN_TAPER: f64 = 0.4;
+
+/// How many blocks below the surface caves start shrinking, so that only a
+/// few of them break through it.
+const SURFACE_FADE: usize = 10;
+
+/// Added to the world's seed for each of the cave noises.
+const CAVE_SEEDS: [u32; 3] = [0x5000_0005, 0x6000_0006, 0x7000_0007];
+/// Added to the world's seed when deciding where bedrock goes.
+const BEDROCK_SEED: u32 = 0x8000_0008;
+
+/// Carves caves out of solid terrain. Two 3D noises are carved where both are
+/// close to zero, which gives long winding tunnels, and a third is carved where
+/// it is high, which gives large caverns deep underground.
+#[derive(Debug, Clone)]
+pub struct CaveCarver {
+    /// The seed of the world.
+    seed: u32,
+    /// The noise whose zero crossings tunnels follow.
+    tunnels: [Perlin; 2],
+    /// The noise caverns are carved from.
+    caverns: Perlin,
+}
+
+impl CaveCarver {
+    /// Creates the carver with the given seed.
+    pub fn new(seed: u32) -> Self {
+        let [first, second, caverns] =
+            CAVE_SEEDS.map(|offset| Perlin::new(seed.wrapping_add(offset)));
+
+        Self {
+            seed,
+            tunnels: [first, second],
+            caverns,
+        }
+    }
+
+    /// Returns true if the block at the given world position is inside a cave.
+    /// `depth` is how far the block is below the surface of its column.
+    pub fn is_cave(&self, (x, y, z): (i32, i32, i32), depth: usize) -> bool {
+        if y < CAVE_FLOOR as i32 {
+            return false;
+        }
+
+        // Shrinks caves close to the surface
+        let fade = ((depth + 1) as f64 / (SURFACE_FADE + 1) as f64).min(1.0);
+
+        let position = [
+            x as f64 * TUNNEL_SCALE,
+            y as f64 * TUNNEL_SCALE * TUNNEL_SQUASH,
+            z as f64 * TUNNEL_SCALE,
+        ];
+
+        let radius = TUNNEL_RADIUS * fade;
+        if self.tunnels[0].get(position).abs() < radius
+            && self.tunnels[1].get(position).abs() < radius
+        {
+            return true;
+        }
+
+        if y >= CAVERN_CEILING as i32 {
+            return false;
+        }
+
+        let height = y as f64 / CAVERN_CEILING as f64;
+        let threshold = CAVERN_THRESHOLD + (height * height + 1.0 - fade) * CAVERN_TAPER;
+
+        let cavern = self.caverns.get([
+            x as f64 * CAVERN_SCALE,
+            y as f64 * CAVERN_SCALE,
+            z as f64 * CAVERN_SCALE,
+        ]);
+
+        cavern > threshold
+    }
+
+    /// Fills the bottom layer of a chunk with bedrock, and scatters bedrock
+    /// through the layers above it.
+    pub fn place_bedrock(&self, chunk: &mut Chunk, bedrock: VoxelKind) {
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+
+                for y in 0..BEDROCK_LAYERS {
+                    let chance = 1.0 - y as f64 / BEDROCK_LAYERS as f64;
+                    let seed = self.seed.wrapping_add(BEDROCK_SEED).wrapping_add(y as u32);
+
+                    if y == 0 || column_random(seed, wx, wz) < chance {
+                        chunk.set((x, y, z), bedrock);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Carves caves out of the solid blocks of a chunk, given a function that
+    /// returns the height of the column at a position in the chunk.
+    /// Returns the number of blocks that were carved.
+    pub fn carve(&self, chunk: &mut Chunk, height: impl Fn(usize, usize) -> usize) -> usize {
+        let mut carved = 0;
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let height = height(x, z).min(CHUNK_HEIGHT);
+
+                for y in CAVE_FLOOR..height {
+                    let is_solid = chunk
+                        .get((x, y, z))
+                        .is_some_and(|voxel| voxel.kind != VoxelKind::AIR);
+
+                    if is_solid && self.is_cave(chunk.world_position((x, y, z)), height - 1 - y) {
+                        chunk.set((x, y, z), VoxelKind::AIR);
+                        carved += 1;
+                    }
+                }
+            }
+        }
+
+        carved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::block_registry;
+
+    /// The height of the solid terrain the caves are carved from.
+    const TERRAIN_HEIGHT: usize = 60;
+
+    /// Fills a chunk with stone on top of bedrock, and carves it with a fixed seed.
+    fn carve_chunk(position: (i32, i32)) -> (Chunk, usize) {
+        crate::init_test_globals();
+        let registry = block_registry();
+
+        let mut chunk = Chunk::new(position);
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                for y in 0..TERRAIN_HEIGHT {
+                    chunk.set((x, y, z), registry.kind("stone"));
+                }
+            }
+        }
+
+        let carver = CaveCarver::new(5);
+        carver.place_bedrock(&mut chunk, registry.kind("bedrock"));
+        let carved = carver.carve(&mut chunk, |_, _| TERRAIN_HEIGHT);
+
+        (chunk, carved)
+    }
+
+    #[test]
+    fn caves_carve_a_fixed_number_of_blocks() {
+        let (chunk, carved) = carve_chunk((-2, 1));
+
+        let mut air = 0;
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                for y in 0..TERRAIN_HEIGHT {
+                    if chunk.get((x, y, z)).unwrap().kind == VoxelKind::AIR {
+                        air += 1;
+                    }
+                }
+            }
+        }
+
+        assert_eq!(carved, 8207);
+        assert_eq!(air, carved);
+    }
+
+    #[test]
+    fn caves_leave_the_bedrock_floor_intact() {
+        crate::init_test_globals();
+        let bedrock = block_registry().kind("bedrock");
+
+        for position in [(-2, 1), (-2, 0), (3, -3)] {
+            let (chunk, _) = carve_chunk(position);
+
+            for x in 0..CHUNK_WIDTH {
+                for z in 0..CHUNK_WIDTH {
+                    assert_eq!(chunk.get((x, 0, z)).unwrap().kind, bedrock);
+
+                    for y in 0..CAVE_FLOOR {
+                        assert_ne!(chunk.get((x, y, z)).unwrap().kind, VoxelKind::AIR);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn caves_are_the_same_for_the_same_seed() {
+        let (first, first_carved) = carve_chunk((-2, 1));
+        let (second, second_carved) = carve_chunk((-2, 1));
+
+        assert_eq!(first_carved, second_carved);
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                for y in 0..CHUNK_HEIGHT {
+                    assert_eq!(first.get((x, y, z)), second.get((x, y, z)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use rand::Rng;
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::{Config, ConfigTable},
+    registry::block_registry,
+    systems::generators::features::ChunkRng,
+    utils::{world_to_chunk_coordinate, world_to_chunk_position},
+    voxel::{Voxel, VoxelKind},
+    world::World,
+};
+
+/// A single block of a decoration, at a world position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub position: (i32, i32, i32),
+    pub voxel: Voxel,
+}
+
+impl Placement {
+    /// Returns the position of the chunk the block lands in.
+    pub fn chunk_position(&self) -> (i32, i32) {
+        world_to_chunk_position(self.position.0, self.position.2)
+    }
+}
+
+/// How firmly a block holds its place against decorations: 0 for blocks that
+/// can't be collided with, 1 for transparent blocks like leaves, and 2 for
+/// opaque blocks.
+fn rank(kind: VoxelKind) -> u8 {
+    let registry = block_registry();
+
+    if !registry.is_solid(kind) {
+        0
+    } else if registry.is_transparent(kind) {
+        1
+    } else {
+        2
+    }
+}
+
+/// Returns true if a block of a decoration replaces the block already there.
+///
+/// Opaque blocks are never replaced. Otherwise the block with the higher rank
+/// wins, with ties broken by ID, so wherever decorations overlap the result is
+/// the same no matter which one is placed first.
+pub fn can_replace(existing: VoxelKind, new: VoxelKind) -> bool {
+    let existing_rank = rank(existing);
+
+    existing_rank < 2 && (rank(new), new.0) > (existing_rank, existing.0)
+}
+
+/// Places a block into the chunk it lands in, if it can replace the block
+/// already there. Returns true if the block was placed.
+pub fn place_in_chunk(chunk: &mut Chunk, placement: &Placement) -> bool {
+    let (x, y, z) = placement.position;
+
+    if placement.chunk_position() != chunk.position || !(0..CHUNK_HEIGHT as i32).contains(&y) {
+        return false;
+    }
+
+    let position = world_to_chunk_coordinate(x, y, z);
+    let existing = chunk
+        .get(position)
+        .map_or(VoxelKind::AIR, |voxel| voxel.kind);
+
+    if !can_replace(existing, placement.voxel.kind) {
+        return false;
+    }
+
+    chunk.set(position, placement.voxel);
+    true
+}
+
+/// Places blocks into the chunks of a world, marking them as modified and
+/// needing a remesh. Returns the blocks whose chunks haven't been built.
+pub fn place_in_world(world: &mut World, placements: Vec<Placement>) -> Vec<Placement> {
+    let mut missing = Vec::new();
+
+    for placement in placements {
+        if !world.contains_chunk(placement.chunk_position()) {
+            missing.push(placement);
+            continue;
+        }
+
+        let (x, y, z) = placement.position;
+
+        if world
+            .get_block(x, y, z)
+            .is_some_and(|existing| can_replace(existing.kind, placement.voxel.kind))
+        {
+            world.set_block(x, y, z, placement.voxel);
+        }
+    }
+
+    missing
+}
+
+/// A structure made of several blocks, placed on top of the terrain.
+///
+/// Only the transparent blocks of a decoration may cross into neighbouring
+/// chunks, since those are the only ones where the order they are placed in
+/// doesn't matter. Decorations are kept far enough from the chunk's borders
+/// that their opaque blocks never do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoration {
+    /// A trunk of logs, topped with a canopy of leaves.
+    Tree {
+        log: VoxelKind,
+        leaves: VoxelKind,
+        /// The possible heights of the trunk.
+        heights: RangeInclusive<usize>,
+        /// How far the widest layers of leaves reach from the trunk.
+        canopy_radius: usize,
+    },
+
+    /// A rough ball of a block, half sunk into the ground.
+    Boulder { block: VoxelKind, radius: usize },
+}
+
+impl Decoration {
+    /// Parses a decoration from a `[[decoration]]` table.
+    /// Returns the decoration along with its name.
+    pub fn from_table(table: &ConfigTable) -> Result<(String, Self), String> {
+        let name = table
+            .get_str("name")
+            .ok_or("Decoration is missing a 'name'")?
+            .to_string();
+
+        let block = |key: &str, default: &str| {
+            let block = table.get_str(key).unwrap_or(default);
+
+            block_registry()
+                .by_name(block)
+                .ok_or_else(|| format!("Decoration '{}': Unknown block '{}'", name, block))
+        };
+
+        let integer = |key: &str, default: usize| match table.get(key) {
+            Some(value) => value
+                .as_integer()
+                .and_then(|value| usize::try_from(value).ok())
+                .ok_or_else(|| format!("Decoration '{}' has an invalid '{}'", name, key)),
+            None => Ok(default),
+        };
+
+        let decoration = match table.get_str("type") {
+            Some("tree") => {
+                let min_height = integer("min_height", 4)?.max(1);
+                let max_height = integer("max_height", 6)?;
+                if max_height < min_height {
+                    return Err(format!(
+                        "Decoration '{}' must have a 'max_height' of at least its 'min_height'",
+                        name
+                    ));
+                }
+
+                Self::Tree {
+                    log: block("log", "log")?,
+                    leaves: block("leaves", "leaves")?,
+                    heights: min_height..=max_height,
+                    canopy_radius: integer("canopy_radius", 2)?,
+                }
+            }
+            Some("boulder") => Self::Boulder {
+                block: block("block", "stone")?,
+                radius: integer("radius", 1)?.max(1),
+            },
+            Some(other) => {
+                return Err(format!(
+                    "Decoration '{}' has an unknown type '{}'",
+                    name, other
+                ))
+            }
+            None => return Err(format!("Decoration '{}' is missing a 'type'", name)),
+        };
+
+        if decoration.reach() * 2 >= CHUNK_WIDTH {
+            return Err(format!("Decoration '{}' is too wide", name));
+        }
+
+        Ok((name, decoration))
+    }
+
+    /// Returns how far the opaque blocks of the decoration reach from the
+    /// column it is placed on.
+    pub fn reach(&self) -> usize {
+        match self {
+            Self::Tree { .. } => 0,
+            Self::Boulder { radius, .. } => *radius,
+        }
+    }
+
+    /// Returns true if the decoration can be placed on the given column of a
+    /// chunk without its opaque blocks crossing into a neighbouring chunk.
+    pub fn fits(&self, (x, z): (usize, usize)) -> bool {
+        let reach = self.reach();
+        let range = reach..CHUNK_WIDTH - reach;
+
+        range.contains(&x) && range.contains(&z)
+    }
+
+    /// Adds the blocks of the decoration to `placements`, standing on top of
+    /// the block below `origin`.
+    pub fn place(
+        &self,
+        origin: (i32, i32, i32),
+        rng: &mut ChunkRng,
+        placements: &mut Vec<Placement>,
+    ) {
+        let (x, y, z) = origin;
+
+        let mut add = |position: (i32, i32, i32), kind: VoxelKind| {
+            placements.push(Placement {
+                position,
+                voxel: kind.into(),
+            });
+        };
+
+        match self {
+            Self::Tree {
+                log,
+                leaves,
+                heights,
+                canopy_radius,
+            } => {
+                let top = y + rng.gen_range(heights.clone()) as i32 - 1;
+
+                for y in y..=top {
+                    add((x, y, z), *log);
+                }
+
+                // Two wide layers of leaves around the top of the trunk, and
+                // two narrower ones above them
+                for dy in -2..=1 {
+                    let radius = if dy < 0 {
+                        *canopy_radius as i32
+                    } else {
+                        (*canopy_radius as i32 - 1).max(1)
+                    };
+
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            // Some of the corners are left out, to round the canopy
+                            let is_corner = dx.abs() == radius && dz.abs() == radius;
+                            if is_corner && (dy > 0 || rng.gen_bool(0.5)) {
+                                continue;
+                            }
+
+                            add((x + dx, top + dy, z + dz), *leaves);
+                        }
+                    }
+                }
+            }
+            Self::Boulder { block, radius } => {
+                let radius = *radius as i32;
+                let center = y + radius / 2 - 1;
+
+                for dx in -radius..=radius {
+                    for dy in -radius..=radius {
+                        for dz in -radius..=radius {
+                            let distance = dx * dx + dy * dy + dz * dz;
+
+                            // Blocks on the outside are left out at random,
+                            // so boulders aren't perfect balls
+                            let is_edge = distance > radius * radius - radius;
+                            if distance > radius * radius || (is_edge && rng.gen_bool(0.4)) {
+                                continue;
+                            }
+
+                            add((x + dx, center + dy, z + dz), *block);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reads the decorations from the `[[decoration]]` tables of a config,
+/// keyed by name.
+pub fn load_decorations(config: &Config) -> Result<HashMap<String, Decoration>, String> {
+    let mut decorations = HashMap::new();
+
+    for table in config.arrays.get("decoration").into_iter().flatten() {
+        let (name, decoration) = Decoration::from_table(table)?;
+
+        if decorations.insert(name.clone(), decoration).is_some() {
+            return Err(format!("Duplicate decoration name '{}'", name));
+        }
+    }
+
+    Ok(decorations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::ConfigTable,
+        systems::generators::{generator_registry, GenContext},
+    };
+
+    /// Generates and decorates chunks in the given order, the way the chunk
+    /// manager does. Returns the world and how many decoration blocks landed
+    /// in another of the chunks than the one they were generated in.
+    fn generate_in_order(positions: &[(i32, i32)]) -> (World, usize) {
+        crate::init_test_globals();
+
+        let generator = generator_registry()
+            .create("biomes", 11, &ConfigTable::new())
+            .unwrap();
+        let ctx = GenContext::new(block_registry());
+
+        let mut world = World::new();
+        let mut pending = HashMap::<_, Vec<_>>::new();
+        let mut crossing = 0;
+
+        for &position in positions {
+            let mut chunk = Chunk::new(position);
+            generator.generate(&mut chunk, &ctx);
+            let placements = generator.decorate(&mut chunk, &ctx);
+
+            // Blocks from neighbours that were generated first
+            for placement in pending.remove(&position).unwrap_or_default() {
+                place_in_chunk(&mut chunk, &placement);
+            }
+
+            world.insert(chunk);
+
+            crossing += placements
+                .iter()
+                .map(|placement| placement.chunk_position())
+                .filter(|chunk| *chunk != position && positions.contains(chunk))
+                .count();
+
+            for placement in place_in_world(&mut world, placements) {
+                pending
+                    .entry(placement.chunk_position())
+                    .or_default()
+                    .push(placement);
+            }
+        }
+
+        (world, crossing)
+    }
+
+    #[test]
+    fn decorations_are_the_same_in_any_chunk_order() {
+        let mut positions = Vec::new();
+        for x in -2..2 {
+            for z in -2..2 {
+                positions.push((x, z));
+            }
+        }
+
+        let (first, crossing) = generate_in_order(&positions);
+
+        // Every other chunk first, then the rest backwards
+        let mut reordered = positions.iter().step_by(2).copied().collect::<Vec<_>>();
+        reordered.extend(positions.iter().skip(1).step_by(2).rev());
+        let (second, _) = generate_in_order(&reordered);
+
+        // The test needs decorations that cross chunk borders
+        assert!(crossing > 0);
+
+        for (cx, cz) in positions {
+            let a = first.get_chunk((cx, cz)).unwrap();
+            let b = second.get_chunk((cx, cz)).unwrap();
+
+            for x in 0..CHUNK_WIDTH {
+                for y in 0..CHUNK_HEIGHT {
+                    for z in 0..CHUNK_WIDTH {
+                        assert_eq!(
+                            a.get((x, y, z)),
+                            b.get((x, y, z)),
+                            "{:?}",
+                            a.world_position((x, y, z))
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt::Debug, ops::Range};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::{Config, ConfigTable},
+    registry::block_registry,
+    systems::generators::hash_position,
+    voxel::VoxelKind,
+};
+
+/// The default location of the ore definitions.
+pub const ORES_PATH: &str = "./assets/ores.toml";
+
+/// The random number generator used while placing features in a chunk.
+/// Unlike `rand::rngs::StdRng`, its output never changes between versions,
+/// so worlds keep generating the same way.
+pub type ChunkRng = ChaCha8Rng;
+
+/// Creates the random number generator for one feature in one chunk. It only
+/// depends on the world's seed, the chunk's position and the feature's index,
+/// so chunks get the same features no matter when they are generated.
+pub fn chunk_rng(seed: u32, position: (i32, i32), feature: u32) -> ChunkRng {
+    let hash = hash_position(seed, position.0, position.1);
+
+    ChunkRng::seed_from_u64(hash ^ (feature as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// Something placed into a chunk after its terrain has been generated.
+pub trait Feature: Debug + Send + Sync {
+    /// Places the feature into a chunk, using only the given random number
+    /// generator for randomness. Returns the number of blocks that were placed.
+    fn place(&self, chunk: &mut Chunk, rng: &mut ChunkRng) -> usize;
+}
+
+/// Places each feature into a chunk in order, each with its own random number
+/// generator, so adding a feature doesn't change where the others are placed.
+/// Returns the number of blocks that were placed.
+pub fn place_features(chunk: &mut Chunk, seed: u32, features: &[Box<dyn Feature>]) -> usize {
+    let mut placed = 0;
+
+    for (index, feature) in features.iter().enumerate() {
+        let mut rng = chunk_rng(seed, chunk.position, index as u32);
+        placed += feature.place(chunk, &mut rng);
+    }
+
+    placed
+}
+
+/// Blobs of a block scattered through the ground, such as ores.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OreVein {
+    /// The block veins are made of.
+    pub block: VoxelKind,
+
+    /// The blocks a vein can replace. Anything else, including air, is left alone.
+    pub replaces: Vec<VoxelKind>,
+
+    /// The heights veins can start at.
+    pub heights: Range<usize>,
+
+    /// The average number of veins in each chunk.
+    pub veins_per_chunk: f64,
+
+    /// The number of blocks in each vein, before any are left out because they
+    /// can't be replaced.
+    pub size: usize,
+}
+
+impl OreVein {
+    /// Parses a vein from an `[[ore]]` table.
+    pub fn from_table(table: &ConfigTable) -> Result<Self, String> {
+        let name = table.get_str("block").ok_or("Ore is missing a 'block'")?;
+
+        let block = |name: &str| {
+            block_registry()
+                .by_name(name)
+                .ok_or_else(|| format!("Unknown block '{}'", name))
+        };
+
+        let replaces = table
+            .get_strs("replaces")
+            .unwrap_or_else(|| vec!["stone"])
+            .into_iter()
+            .map(block)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let integer = |key: &str, default: usize| match table.get(key) {
+            Some(value) => value
+                .as_integer()
+                .and_then(|value| usize::try_from(value).ok())
+                .ok_or_else(|| format!("Ore '{}' has an invalid '{}'", name, key)),
+            None => Ok(default),
+        };
+
+        let min_height = integer("min_height", 0)?;
+        let max_height = integer("max_height", CHUNK_HEIGHT)?.min(CHUNK_HEIGHT);
+        if min_height >= max_height {
+            return Err(format!(
+                "Ore '{}' must have a 'min_height' below its 'max_height'",
+                name
+            ));
+        }
+
+        let veins_per_chunk = match table.get("veins_per_chunk") {
+            Some(value) => value
+                .as_float()
+                .filter(|value| *value >= 0.0)
+                .ok_or_else(|| format!("Ore '{}' has an invalid 'veins_per_chunk'", name))?,
+            None => 1.0,
+        };
+
+        Ok(Self {
+            block: block(name)?,
+            replaces,
+            heights: min_height..max_height,
+            veins_per_chunk,
+            size: integer("size", 8)?,
+        })
+    }
+}
+
+impl Feature for OreVein {
+    /// Each vein is a random walk from a random starting block. Any part of a
+    /// vein that wanders out of the chunk is cut off.
+    fn place(&self, chunk: &mut Chunk, rng: &mut ChunkRng) -> usize {
+        // The fraction of a vein is the chance of placing one more
+        let mut veins = self.veins_per_chunk as usize;
+        if rng.gen_bool(self.veins_per_chunk.fract()) {
+            veins += 1;
+        }
+
+        let mut placed = 0;
+
+        for _ in 0..veins {
+            let mut position = (
+                rng.gen_range(0..CHUNK_WIDTH as i32),
+                rng.gen_range(self.heights.start as i32..self.heights.end as i32),
+                rng.gen_range(0..CHUNK_WIDTH as i32),
+            );
+
+            for _ in 0..self.size {
+                // Negative coordinates wrap around to huge ones, which `get` ignores
+                let (x, y, z) = position;
+                let pos = (x as usize, y as usize, z as usize);
+
+                if chunk
+                    .get(pos)
+                    .is_some_and(|voxel| self.replaces.contains(&voxel.kind))
+                {
+                    chunk.set(pos, self.block);
+                    placed += 1;
+                }
+
+                match rng.gen_range(0..6) {
+                    0 => position.0 += 1,
+                    1 => position.0 -= 1,
+                    2 => position.1 += 1,
+                    3 => position.1 -= 1,
+                    4 => position.2 += 1,
+                    _ => position.2 -= 1,
+                }
+            }
+        }
+
+        placed
+    }
+}
+
+/// Reads the ore veins from the `[[ore]]` tables of a config.
+pub fn load_ores(config: &Config) -> Result<Vec<OreVein>, String> {
+    config
+        .arrays
+        .get("ore")
+        .into_iter()
+        .flatten()
+        .map(OreVein::from_table)
+        .collect()
+}
@@ -0,0 +1,359 @@
+use noise::{NoiseFn, Perlin};
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::{ConfigTable, ConfigValue},
+    systems::generators::{
+        fill_column,
+        water::{flood, sea_level_param},
+        GenContext, TerrainGenerator,
+    },
+};
+
+/// How quickly the weight of later ridged octaves falls off in valleys.
+const RIDGE_GAIN: f64 = 2.0;
+
+/// How far positions are moved by the warp noise by default, in blocks.
+const DEFAULT_WARP_STRENGTH: f64 = 24.0;
+/// The default frequency of the warp noise, in cycles per block.
+const DEFAULT_WARP_SCALE: f64 = 0.004;
+
+/// The number of octaves used for the noise that warps positions.
+const WARP_OCTAVES: u32 = 2;
+
+/// Added to the seed of the noise that warps x coordinates.
+const WARP_X_SEED: u32 = 0x5bd1_e995;
+/// Added to the seed of the noise that warps z coordinates.
+const WARP_Z_SEED: u32 = 0x27d4_eb2f;
+
+/// How several octaves of perlin noise are layered on top of each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FractalSettings {
+    /// The frequency of the first octave, in cycles per block.
+    pub scale: f64,
+    /// The number of layers of noise.
+    pub octaves: u32,
+    /// How much the frequency is multiplied by for each octave.
+    pub lacunarity: f64,
+    /// How much the amplitude is multiplied by for each octave.
+    pub persistence: f64,
+    /// Whether each octave is folded into sharp ridges, like mountain ranges.
+    pub ridged: bool,
+}
+
+impl Default for FractalSettings {
+    fn default() -> Self {
+        Self {
+            scale: 0.005,
+            octaves: 5,
+            lacunarity: 2.0,
+            persistence: 0.5,
+            ridged: false,
+        }
+    }
+}
+
+impl FractalSettings {
+    /// Reads the `scale`, `octaves`, `lacunarity`, `persistence` and `ridged`
+    /// parameters. Missing parameters keep their default values.
+    pub fn from_params(params: &ConfigTable) -> Result<Self, String> {
+        let defaults = Self::default();
+
+        let octaves = match params.get("octaves") {
+            Some(octaves) => octaves
+                .as_integer()
+                .and_then(|octaves| u32::try_from(octaves).ok())
+                .filter(|octaves| (1..=16).contains(octaves))
+                .ok_or("'octaves' must be between 1 and 16")?,
+            None => defaults.octaves,
+        };
+
+        let ridged = match params.get("ridged") {
+            Some(ridged) => ridged.as_bool().ok_or("'ridged' must be a boolean")?,
+            None => defaults.ridged,
+        };
+
+        Ok(Self {
+            scale: float_param(params, "scale", defaults.scale)?,
+            octaves,
+            lacunarity: float_param(params, "lacunarity", defaults.lacunarity)?,
+            persistence: float_param(params, "persistence", defaults.persistence)?,
+            ridged,
+        })
+    }
+
+    /// Writes the settings as parameters.
+    pub fn write_params(&self, params: &mut ConfigTable) {
+        params.insert("scale", self.scale);
+        params.insert("octaves", self.octaves as i64);
+        params.insert("lacunarity", self.lacunarity);
+        params.insert("persistence", self.persistence);
+        params.insert("ridged", self.ridged);
+    }
+}
+
+/// Several octaves of 2D perlin noise added together, each with a higher
+/// frequency and a lower amplitude than the last.
+#[derive(Debug, Clone)]
+pub struct FractalNoise {
+    /// How the octaves are layered.
+    pub settings: FractalSettings,
+    /// The noise of each octave, each with its own seed.
+    octaves: Vec<Perlin>,
+}
+
+impl FractalNoise {
+    /// Creates the noise with the given seed.
+    pub fn new(seed: u32, settings: FractalSettings) -> Self {
+        let octaves = (0..settings.octaves)
+            .map(|octave| Perlin::new(seed.wrapping_add(octave)))
+            .collect();
+
+        Self { settings, octaves }
+    }
+
+    /// Samples the noise at a position in blocks.
+    /// The result is between -1 and 1.
+    pub fn sample(&self, x: f64, z: f64) -> f64 {
+        let mut frequency = self.settings.scale;
+        let mut amplitude = 1.0;
+        let mut weight = 1.0;
+
+        let mut total = 0.0;
+        let mut max = 0.0;
+
+        for noise in self.octaves.iter() {
+            let value = noise.get([x * frequency, z * frequency]);
+
+            if self.settings.ridged {
+                // Peaks are where the noise crosses zero. Each octave is weighted
+                // by the last, so valleys stay smooth while ridges get rougher
+                let ridge = (1.0 - value.abs()).powi(2) * weight;
+                weight = (ridge * RIDGE_GAIN).clamp(0.0, 1.0);
+
+                total += ridge * amplitude;
+            } else {
+                total += value * amplitude;
+            }
+
+            max += amplitude;
+            frequency *= self.settings.lacunarity;
+            amplitude *= self.settings.persistence;
+        }
+
+        let value = if max > 0.0 { total / max } else { 0.0 };
+
+        if self.settings.ridged {
+            (value * 2.0 - 1.0).clamp(-1.0, 1.0)
+        } else {
+            value.clamp(-1.0, 1.0)
+        }
+    }
+}
+
+/// Maps noise values to heights by interpolating linearly between points.
+/// Values outside of the first and last points are clamped to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightCurve {
+    /// The noise value and height of each point, sorted by noise value.
+    points: Vec<(f64, f64)>,
+}
+
+impl Default for HeightCurve {
+    /// Low, wide plains that rise into hills, with a cliff up to the mountains.
+    fn default() -> Self {
+        Self {
+            points: vec![
+                (-1.0, 20.0),
+                (-0.4, 36.0),
+                (0.15, 44.0),
+                (0.4, 64.0),
+                (0.5, 88.0),
+                (1.0, 118.0),
+            ],
+        }
+    }
+}
+
+impl HeightCurve {
+    /// Creates a curve through the given points, in any order.
+    /// Fails if there are no points.
+    pub fn new(mut points: Vec<(f64, f64)>) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err("A height curve needs at least one point".to_string());
+        }
+
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self { points })
+    }
+
+    /// Reads a curve from an array of `[noise, height]` pairs.
+    pub fn from_value(value: &ConfigValue) -> Result<Self, String> {
+        let points = value
+            .as_array()
+            .ok_or("A height curve must be an array")?
+            .iter()
+            .map(|point| match point.as_array() {
+                Some([noise, height]) => Some((noise.as_float()?, height.as_float()?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or("Each point of a height curve must be a [noise, height] pair")?;
+
+        Self::new(points)
+    }
+
+    /// Converts the curve into an array of `[noise, height]` pairs.
+    pub fn to_value(&self) -> ConfigValue {
+        self.points
+            .iter()
+            .map(|(noise, height)| vec![*noise, *height])
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Returns the height at the given noise value.
+    pub fn evaluate(&self, value: f64) -> f64 {
+        let index = self.points.partition_point(|(noise, _)| *noise < value);
+
+        if index == 0 {
+            return self.points[0].1;
+        }
+        if index == self.points.len() {
+            return self.points[index - 1].1;
+        }
+
+        let (x0, y0) = self.points[index - 1];
+        let (x1, y1) = self.points[index];
+
+        if x1 - x0 <= f64::EPSILON {
+            return y1;
+        }
+
+        y0 + (y1 - y0) * (value - x0) / (x1 - x0)
+    }
+}
+
+/// Generates columns from fractal noise, optionally warped by more noise,
+/// whose value is turned into a height by a curve.
+#[derive(Debug, Clone)]
+pub struct FractalGenerator {
+    /// The noise deciding the height of each column.
+    pub noise: FractalNoise,
+    /// How far positions are moved by the warp noise, in blocks.
+    /// Zero disables warping.
+    pub warp_strength: f64,
+    /// The noise that warps the x and z coordinates of each column.
+    warp: [FractalNoise; 2],
+    /// Maps the noise of each column to its height.
+    pub curve: HeightCurve,
+    /// Columns lower than this are covered with water. Zero means no water.
+    pub sea_level: usize,
+}
+
+impl FractalGenerator {
+    /// Creates the generator with the given seed.
+    pub fn new(
+        seed: u32,
+        settings: FractalSettings,
+        warp_strength: f64,
+        warp_scale: f64,
+        curve: HeightCurve,
+        sea_level: usize,
+    ) -> Self {
+        let warp_settings = FractalSettings {
+            scale: warp_scale,
+            octaves: WARP_OCTAVES,
+            ridged: false,
+            ..settings
+        };
+
+        Self {
+            noise: FractalNoise::new(seed, settings),
+            warp_strength,
+            warp: [
+                FractalNoise::new(seed.wrapping_add(WARP_X_SEED), warp_settings),
+                FractalNoise::new(seed.wrapping_add(WARP_Z_SEED), warp_settings),
+            ],
+            curve,
+            sea_level,
+        }
+    }
+
+    /// Creates the generator from its parameters, which are those of
+    /// `FractalSettings` along with `warp_strength`, `warp_scale`, `curve` and
+    /// `sea_level`. Every parameter is optional.
+    pub fn from_params(seed: u32, params: &ConfigTable) -> Result<Self, String> {
+        let settings = FractalSettings::from_params(params)?;
+
+        let curve = match params.get("curve") {
+            Some(curve) => HeightCurve::from_value(curve)?,
+            None => HeightCurve::default(),
+        };
+
+        Ok(Self::new(
+            seed,
+            settings,
+            float_param(params, "warp_strength", DEFAULT_WARP_STRENGTH)?,
+            float_param(params, "warp_scale", DEFAULT_WARP_SCALE)?,
+            curve,
+            sea_level_param(params)?,
+        ))
+    }
+
+    /// Returns the height of the column at the given world position.
+    pub fn height(&self, x: i32, z: i32) -> usize {
+        let (mut x, mut z) = (x as f64, z as f64);
+
+        if self.warp_strength != 0.0 {
+            let (warp_x, warp_z) = (self.warp[0].sample(x, z), self.warp[1].sample(x, z));
+
+            x += warp_x * self.warp_strength;
+            z += warp_z * self.warp_strength;
+        }
+
+        let height = self.curve.evaluate(self.noise.sample(x, z));
+
+        height.round().clamp(0.0, CHUNK_HEIGHT as f64) as usize
+    }
+}
+
+impl TerrainGenerator for FractalGenerator {
+    fn name(&self) -> &'static str {
+        "fractal"
+    }
+
+    fn params(&self) -> ConfigTable {
+        let mut params = ConfigTable::new();
+
+        self.noise.settings.write_params(&mut params);
+        params.insert("warp_strength", self.warp_strength);
+        params.insert("warp_scale", self.warp[0].settings.scale);
+        params.insert("curve", self.curve.to_value());
+        params.insert("sea_level", self.sea_level as i64);
+
+        params
+    }
+
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext) {
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+
+                fill_column(chunk, ctx, (x, z), 0..self.height(wx, wz));
+            }
+        }
+
+        flood(chunk, ctx, self.sea_level, |x, z| self.height(x, z));
+    }
+}
+
+/// Reads an optional float parameter, which may be written as an integer.
+fn float_param(params: &ConfigTable, key: &str, default: f64) -> Result<f64, String> {
+    match params.get(key) {
+        Some(value) => value
+            .as_float()
+            .ok_or_else(|| format!("'{}' must be a number", key)),
+        None => Ok(default),
+    }
+}
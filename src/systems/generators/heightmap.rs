@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use image::{io::Reader as ImageReader, DynamicImage};
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::ConfigTable,
+    systems::generators::{fill_column, GenContext, TerrainGenerator},
+};
+
+/// How columns outside of a heightmap's image are generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// The image repeats in every direction.
+    Tile,
+    /// The pixels on the edge of the image stretch out forever.
+    Clamp,
+}
+
+impl EdgeMode {
+    /// Returns the name of the mode, as used in generator parameters.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            EdgeMode::Tile => "tile",
+            EdgeMode::Clamp => "clamp",
+        }
+    }
+
+    /// Gets a mode from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [EdgeMode::Tile, EdgeMode::Clamp]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+
+    /// Maps a world coordinate to a pixel coordinate in an image of the given size.
+    fn pixel(&self, coordinate: i32, size: u32) -> usize {
+        match self {
+            EdgeMode::Tile => coordinate.rem_euclid(size as i32) as usize,
+            EdgeMode::Clamp => coordinate.clamp(0, size as i32 - 1) as usize,
+        }
+    }
+}
+
+/// The column heights read from a grayscale image. Each pixel covers one
+/// column, with the image's x axis along the world's x axis and its y axis
+/// along the world's z axis. Black is an empty column, and white is a column
+/// that reaches the top of the chunk.
+#[derive(Debug, Clone)]
+pub struct HeightmapGenerator {
+    /// The path of the image.
+    pub path: String,
+
+    /// How columns outside of the image are generated.
+    pub edge_mode: EdgeMode,
+
+    /// The width and height of the image, in pixels.
+    pub size: (u32, u32),
+
+    /// The height of each column, ordered by row.
+    heights: Arc<Vec<u16>>,
+}
+
+impl HeightmapGenerator {
+    /// Loads a heightmap from an image file.
+    pub fn load(path: &str, edge_mode: EdgeMode) -> Result<Self, String> {
+        let image = ImageReader::open(path)
+            .map_err(|err| format!("Failed to open heightmap '{}': {}", path, err))?
+            .decode()
+            .map_err(|err| format!("Failed to decode heightmap '{}': {}", path, err))?;
+
+        Self::from_image(path, image, edge_mode)
+    }
+
+    /// Creates a heightmap from an image that was loaded from the given path.
+    /// Coloured images are converted to grayscale.
+    pub fn from_image(
+        path: &str,
+        image: DynamicImage,
+        edge_mode: EdgeMode,
+    ) -> Result<Self, String> {
+        let image = image.into_luma16();
+
+        let size = image.dimensions();
+        if size.0 == 0 || size.1 == 0 {
+            return Err(format!("Heightmap '{}' is empty", path));
+        }
+
+        // 16-bit brightness keeps precision for heights from 16-bit images
+        let heights = image
+            .pixels()
+            .map(|pixel| {
+                let brightness = pixel.0[0] as f32 / u16::MAX as f32;
+                (brightness * CHUNK_HEIGHT as f32).round() as u16
+            })
+            .collect();
+
+        Ok(Self {
+            path: path.to_string(),
+            edge_mode,
+            size,
+            heights: Arc::new(heights),
+        })
+    }
+
+    /// Creates the generator from its `path` and optional `edge` parameters.
+    /// The edge mode defaults to clamping.
+    pub fn from_params(params: &ConfigTable) -> Result<Self, String> {
+        let path = params.get_str("path").ok_or("Missing 'path' parameter")?;
+
+        let edge_mode = match params.get_str("edge") {
+            Some(edge) => {
+                EdgeMode::from_name(edge).ok_or_else(|| format!("Unknown edge mode '{}'", edge))?
+            }
+            None => EdgeMode::Clamp,
+        };
+
+        Self::load(path, edge_mode)
+    }
+
+    /// Returns the height of the column at the given world position.
+    pub fn height(&self, x: i32, z: i32) -> usize {
+        let px = self.edge_mode.pixel(x, self.size.0);
+        let py = self.edge_mode.pixel(z, self.size.1);
+
+        self.heights[px + py * self.size.0 as usize] as usize
+    }
+}
+
+impl TerrainGenerator for HeightmapGenerator {
+    fn name(&self) -> &'static str {
+        "heightmap"
+    }
+
+    fn params(&self) -> ConfigTable {
+        let mut params = ConfigTable::new();
+
+        params.insert("path", self.path.as_str());
+        params.insert("edge", self.edge_mode.name());
+
+        params
+    }
+
+    /// Fills the columns of a chunk up to their heights.
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext) {
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+
+                fill_column(chunk, ctx, (x, z), 0..self.height(wx, wz));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GrayImage;
+
+    use super::*;
+    use crate::registry::block_registry;
+
+    /// A 3x2 heightmap, getting brighter along each row.
+    fn heightmap(edge_mode: EdgeMode) -> HeightmapGenerator {
+        let pixels = vec![0, 51, 102, 153, 204, 255];
+        let image = GrayImage::from_raw(3, 2, pixels).unwrap();
+
+        HeightmapGenerator::from_image("test.png", DynamicImage::ImageLuma8(image), edge_mode)
+            .unwrap()
+    }
+
+    #[test]
+    fn brightness_maps_to_height() {
+        let generator = heightmap(EdgeMode::Clamp);
+        assert_eq!(generator.size, (3, 2));
+
+        let heights =
+            [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)].map(|(x, z)| generator.height(x, z));
+        assert_eq!(heights, [0, 26, 51, 77, 102, CHUNK_HEIGHT]);
+    }
+
+    #[test]
+    fn tiled_heightmaps_repeat_in_every_direction() {
+        let generator = heightmap(EdgeMode::Tile);
+
+        assert_eq!(generator.height(-1, 0), 51);
+        assert_eq!(generator.height(3, 0), 0);
+        assert_eq!(generator.height(0, -1), 77);
+        assert_eq!(generator.height(4, 5), 102);
+        assert_eq!(generator.height(-4, -3), CHUNK_HEIGHT);
+        assert_eq!(generator.height(-300, 0), generator.height(0, 0));
+    }
+
+    #[test]
+    fn clamped_heightmaps_stretch_their_edges() {
+        let generator = heightmap(EdgeMode::Clamp);
+
+        assert_eq!(generator.height(-1, 0), 0);
+        assert_eq!(generator.height(3, 0), 51);
+        assert_eq!(generator.height(0, -5), 0);
+        assert_eq!(generator.height(-7, 9), 77);
+        assert_eq!(generator.height(100, 100), CHUNK_HEIGHT);
+        assert_eq!(generator.height(i32::MIN, i32::MAX), 77);
+    }
+
+    #[test]
+    fn columns_are_filled_up_to_their_height() {
+        crate::init_test_globals();
+        let ctx = GenContext::new(block_registry());
+
+        // The chunk west of the origin, which only exists when tiling
+        let generator = heightmap(EdgeMode::Tile);
+        let mut chunk = Chunk::new((-1, 0));
+        generator.generate(&mut chunk, &ctx);
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+                let height = generator.height(wx, wz);
+
+                let surface = chunk.surface(x, z).map(|(y, _)| y + 1);
+                assert_eq!(surface.unwrap_or(0), height, "{:?}", (wx, wz));
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+// Terrain generators fill new chunks with blocks. Each one implements
+// `TerrainGenerator`, and is registered by name in the `GeneratorRegistry`.
+pub mod basic;
+pub mod biome;
+pub mod caves;
+pub mod decorations;
+pub mod features;
+pub mod fractal;
+pub mod heightmap;
+pub mod perlin;
+pub mod vox_model;
+pub mod water;
+
+use std::{collections::BTreeMap, fmt::Debug, ops::Range, sync::Arc};
+
+use crate::{
+    chunk::Chunk,
+    config::{ConfigTable, ConfigValue},
+    registry::BlockRegistry,
+    voxel::VoxelKind,
+    GENERATOR_REGISTRY,
+};
+
+use self::{
+    basic::{EmptyGenerator, FlatPlaneGenerator, SingleVoxelsGenerator},
+    biome::{Biome, BiomeGenerator},
+    decorations::Placement,
+    fractal::FractalGenerator,
+    heightmap::HeightmapGenerator,
+    perlin::{Perlin2dGenerator, Perlin3dGenerator},
+    vox_model::VoxModelGenerator,
+};
+
+/// How many blocks of dirt are placed below the surface.
+pub const DIRT_DEPTH: usize = 3;
+
+/// Fills new chunks with terrain.
+/// Generators must always produce the same blocks for the same chunk.
+pub trait TerrainGenerator: Debug + Send + Sync {
+    /// Returns the name the generator is registered under.
+    fn name(&self) -> &'static str;
+
+    /// Returns the parameters needed to recreate the generator, as stored in
+    /// a world's metadata. The seed is stored separately.
+    fn params(&self) -> ConfigTable {
+        ConfigTable::new()
+    }
+
+    /// Populates a chunk that only contains air.
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext);
+
+    /// Places decorations, such as trees, into a chunk that was just generated.
+    /// They may only depend on the chunk's own terrain, so that chunks get the
+    /// same decorations in whatever order they are generated. Returns the
+    /// blocks that landed in other chunks, to be placed into them.
+    fn decorate(&self, _chunk: &mut Chunk, _ctx: &GenContext) -> Vec<Placement> {
+        Vec::new()
+    }
+
+    /// Returns the biome of the column at the given world position,
+    /// if the generator has biomes.
+    fn biome_at(&self, _x: i32, _z: i32) -> Option<&Biome> {
+        None
+    }
+}
+
+/// The state shared by every generator while it fills a chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct GenContext<'a> {
+    /// The block definitions, used to look up blocks by name.
+    pub registry: &'a BlockRegistry,
+}
+
+impl<'a> GenContext<'a> {
+    /// Creates a context using the given block definitions.
+    pub fn new(registry: &'a BlockRegistry) -> Self {
+        Self { registry }
+    }
+
+    /// Gets the ID of a block from its name.
+    /// Panics if no block has the given name.
+    pub fn block(&self, name: &str) -> VoxelKind {
+        self.registry.kind(name)
+    }
+}
+
+/// Creates a generator from the world's seed and the generator's parameters.
+pub type GeneratorFactory =
+    fn(seed: u32, params: &ConfigTable) -> Result<Arc<dyn TerrainGenerator>, String>;
+
+/// Stores every generator that can be chosen by name.
+#[derive(Debug, Default)]
+pub struct GeneratorRegistry {
+    /// The factory of each generator, keyed by name.
+    factories: BTreeMap<String, GeneratorFactory>,
+}
+
+impl GeneratorRegistry {
+    /// Creates a registry without any generators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry with all of the built in generators.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+
+        let defaults: [(&str, GeneratorFactory); 9] = [
+            ("empty", |_, _| Ok(Arc::new(EmptyGenerator))),
+            ("perlin_2d", |seed, _| {
+                Ok(Arc::new(Perlin2dGenerator::new(seed)))
+            }),
+            ("perlin_3d", |seed, _| {
+                Ok(Arc::new(Perlin3dGenerator::new(seed)))
+            }),
+            ("biomes", |seed, params| {
+                Ok(Arc::new(BiomeGenerator::from_params(seed, params)?))
+            }),
+            ("fractal", |seed, params| {
+                Ok(Arc::new(FractalGenerator::from_params(seed, params)?))
+            }),
+            ("flat_plane", |_, params| {
+                Ok(Arc::new(FlatPlaneGenerator::from_params(params)?))
+            }),
+            ("single_voxels", |_, params| {
+                Ok(Arc::new(SingleVoxelsGenerator::from_params(params)?))
+            }),
+            ("vox_model", |_, params| {
+                Ok(Arc::new(VoxModelGenerator::from_params(params)?))
+            }),
+            ("heightmap", |_, params| {
+                Ok(Arc::new(HeightmapGenerator::from_params(params)?))
+            }),
+        ];
+
+        for (name, factory) in defaults {
+            registry
+                .register(name, factory)
+                .expect("Built in generators have unique names");
+        }
+
+        registry
+    }
+
+    /// Adds a generator to the registry.
+    pub fn register(&mut self, name: &str, factory: GeneratorFactory) -> Result<(), String> {
+        if self.factories.contains_key(name) {
+            return Err(format!("Duplicate generator name '{}'", name));
+        }
+
+        self.factories.insert(name.to_string(), factory);
+        Ok(())
+    }
+
+    /// Creates the generator with the given name. Fails if any of the
+    /// parameters isn't one of the generator's, so misspelled ones aren't lost.
+    pub fn create(
+        &self,
+        name: &str,
+        seed: u32,
+        params: &ConfigTable,
+    ) -> Result<Arc<dyn TerrainGenerator>, String> {
+        let factory = self
+            .factories
+            .get(name)
+            .ok_or_else(|| format!("Unknown generator '{}'", name))?;
+
+        let generator = factory(seed, params)?;
+
+        let known = generator.params();
+        if let Some((key, _)) = params.iter().find(|(key, _)| !known.contains(key)) {
+            return Err(format!(
+                "Unknown parameter '{}' for the '{}' generator",
+                key, name
+            ));
+        }
+
+        Ok(generator)
+    }
+
+    /// Iterates over the names of all generators, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+}
+
+/// Returns the global generator registry.
+/// Panics if the registry has not been created yet.
+pub fn generator_registry() -> &'static GeneratorRegistry {
+    GENERATOR_REGISTRY
+        .get()
+        .expect("The generator registry has not been created")
+}
+
+/// The blocks a column of terrain is made of, from the top down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnLayers {
+    /// The highest block of the column.
+    pub surface: VoxelKind,
+    /// The blocks below the surface.
+    pub filler: VoxelKind,
+    /// How many filler blocks are placed below the surface.
+    pub filler_depth: usize,
+    /// The blocks below the filler, down to the bottom of the column.
+    pub base: VoxelKind,
+}
+
+impl ColumnLayers {
+    /// Grass on top of `DIRT_DEPTH` blocks of dirt, on top of stone.
+    pub fn grassland(ctx: &GenContext) -> Self {
+        Self {
+            surface: ctx.block("grass"),
+            filler: ctx.block("dirt"),
+            filler_depth: DIRT_DEPTH,
+            base: ctx.block("stone"),
+        }
+    }
+
+    /// Fills the blocks of a column in the given range of heights.
+    pub fn fill(&self, chunk: &mut Chunk, (x, z): (usize, usize), heights: Range<usize>) {
+        let top = heights.end.saturating_sub(1);
+
+        for y in heights {
+            let depth = top - y;
+
+            let kind = if depth == 0 {
+                self.surface
+            } else if depth <= self.filler_depth {
+                self.filler
+            } else {
+                self.base
+            };
+
+            chunk.set((x, y, z), kind);
+        }
+    }
+}
+
+/// Fills the blocks of a column in the given range of heights, with grass on
+/// top, followed by `DIRT_DEPTH` blocks of dirt and stone below that.
+pub fn fill_column(
+    chunk: &mut Chunk,
+    ctx: &GenContext,
+    (x, z): (usize, usize),
+    heights: Range<usize>,
+) {
+    ColumnLayers::grassland(ctx).fill(chunk, (x, z), heights);
+}
+
+/// Hashes a seed and a column position into a 64-bit number.
+/// The same inputs always give the same number.
+pub fn hash_position(seed: u32, x: i32, z: i32) -> u64 {
+    // SplitMix64, which mixes nearby positions into unrelated values
+    let mut hash = ((seed as u64) << 32) ^ (x as u32 as u64);
+    hash = hash.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ (z as u32 as u64);
+
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Hashes a seed and a column position into a number from 0 to 1.
+/// The same inputs always give the same number.
+pub fn column_random(seed: u32, x: i32, z: i32) -> f64 {
+    (hash_position(seed, x, z) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Reads a list of three integers, such as a position.
+pub fn integer_triple(value: &ConfigValue) -> Option<[i64; 3]> {
+    match value.as_array()? {
+        [x, y, z] => Some([x.as_integer()?, y.as_integer()?, z.as_integer()?]),
+        _ => None,
+    }
+}
@@ -0,0 +1,97 @@
+use noise::{NoiseFn, Perlin};
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    systems::generators::{GenContext, TerrainGenerator},
+    voxel::VoxelKind,
+};
+
+pub const NOISE_SCALE: f64 = 0.01;
+
+/// Performs a perlin noise generation for the height of each column.
+#[derive(Debug, Clone)]
+pub struct Perlin2dGenerator {
+    noise: Perlin,
+}
+
+impl Perlin2dGenerator {
+    /// Creates the generator with the given seed.
+    pub fn new(seed: u32) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+        }
+    }
+}
+
+impl TerrainGenerator for Perlin2dGenerator {
+    fn name(&self) -> &'static str {
+        "perlin_2d"
+    }
+
+    /// The height of each column is determined by the noise value.
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext) {
+        let grass = ctx.block("grass");
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                let (wx, _, wz) = chunk.world_position((x, 0, z));
+                let pos = (wx as f64 * NOISE_SCALE, wz as f64 * NOISE_SCALE);
+
+                let noise_value = self.noise.get([pos.0, pos.1]) as f32;
+                let noise_value = (noise_value + 1.0) / 2.0;
+                let noise_value = noise_value.clamp(0.0, 1.0);
+
+                let height = (noise_value * CHUNK_HEIGHT as f32) as usize;
+
+                for y in 1..height {
+                    chunk.set((x, y, z), grass);
+                }
+            }
+        }
+    }
+}
+
+/// Performs a perlin noise generation in all 3 dimensions.
+#[derive(Debug, Clone)]
+pub struct Perlin3dGenerator {
+    noise: Perlin,
+}
+
+impl Perlin3dGenerator {
+    /// Creates the generator with the given seed.
+    pub fn new(seed: u32) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+        }
+    }
+}
+
+impl TerrainGenerator for Perlin3dGenerator {
+    fn name(&self) -> &'static str {
+        "perlin_3d"
+    }
+
+    fn generate(&self, chunk: &mut Chunk, ctx: &GenContext) {
+        let grass = ctx.block("grass");
+
+        for x in 0..CHUNK_WIDTH {
+            for z in 0..CHUNK_WIDTH {
+                for y in 0..CHUNK_HEIGHT {
+                    let (wx, wy, wz) = chunk.world_position((x, y, z));
+
+                    let noise_value = self.noise.get([
+                        wx as f64 * NOISE_SCALE,
+                        wy as f64 * NOISE_SCALE,
+                        wz as f64 * NOISE_SCALE,
+                    ]) as f32;
+
+                    if noise_value > 0.0 {
+                        chunk.set((x, y, z), grass);
+                    } else {
+                        chunk.set((x, y, z), VoxelKind::AIR);
+                    }
+                }
+            }
+        }
+    }
+}
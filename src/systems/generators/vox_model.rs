@@ -0,0 +1,98 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT},
+    config::ConfigTable,
+    formats::vox::{VoxFile, VoxMapping, VOX_MAPPING_PATH},
+    systems::generators::{integer_triple, GenContext, TerrainGenerator},
+    utils::{world_to_chunk_coordinate, world_to_chunk_position},
+    voxel::VoxelKind,
+};
+
+/// The blocks of a stamped model, grouped by the chunk they are in.
+type StampedBlocks = HashMap<(i32, i32), Vec<((usize, usize, usize), VoxelKind)>>;
+
+/// Stamps a MagicaVoxel model into otherwise empty chunks, at a fixed origin.
+#[derive(Debug, Clone)]
+pub struct VoxModelGenerator {
+    /// The path of the .vox file.
+    pub path: String,
+
+    /// The path of the palette mapping.
+    pub mapping_path: String,
+
+    /// The world position of the model's minimum corner.
+    pub origin: (i32, i32, i32),
+
+    /// The blocks of the model.
+    blocks: Arc<StampedBlocks>,
+}
+
+impl VoxModelGenerator {
+    /// Loads the first model of a .vox file, and resolves its blocks.
+    pub fn load(path: &str, mapping_path: &str, origin: (i32, i32, i32)) -> Result<Self, String> {
+        let file = VoxFile::load(path).map_err(|err| err.to_string())?;
+        let mapping = VoxMapping::load(mapping_path)?;
+
+        let mut blocks = StampedBlocks::new();
+
+        for ((x, y, z), kind) in file.models[0].blocks(origin, &mapping) {
+            if !(0..CHUNK_HEIGHT as i32).contains(&y) {
+                continue;
+            }
+
+            blocks
+                .entry(world_to_chunk_position(x, z))
+                .or_default()
+                .push((world_to_chunk_coordinate(x, y, z), kind));
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            mapping_path: mapping_path.to_string(),
+            origin,
+            blocks: Arc::new(blocks),
+        })
+    }
+
+    /// Creates the generator from its `path`, `origin` and optional `mapping` parameters.
+    pub fn from_params(params: &ConfigTable) -> Result<Self, String> {
+        let path = params.get_str("path").ok_or("Missing 'path' parameter")?;
+        let mapping = params.get_str("mapping").unwrap_or(VOX_MAPPING_PATH);
+
+        let origin = params
+            .get("origin")
+            .and_then(integer_triple)
+            .and_then(|origin| {
+                let [x, y, z] = origin.map(|c| i32::try_from(c).ok());
+                Some((x?, y?, z?))
+            })
+            .ok_or("The 'origin' parameter must be an [x, y, z] position")?;
+
+        Self::load(path, mapping, origin)
+    }
+}
+
+impl TerrainGenerator for VoxModelGenerator {
+    fn name(&self) -> &'static str {
+        "vox_model"
+    }
+
+    fn params(&self) -> ConfigTable {
+        let mut params = ConfigTable::new();
+        let (x, y, z) = self.origin;
+
+        params.insert("path", self.path.as_str());
+        params.insert("mapping", self.mapping_path.as_str());
+        params.insert("origin", vec![x as i64, y as i64, z as i64]);
+
+        params
+    }
+
+    /// Places the part of the model that lies inside the chunk.
+    fn generate(&self, chunk: &mut Chunk, _ctx: &GenContext) {
+        for (pos, kind) in self.blocks.get(&chunk.position).into_iter().flatten() {
+            chunk.set(*pos, *kind);
+        }
+    }
+}
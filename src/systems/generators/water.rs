@@ -0,0 +1,110 @@
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    config::ConfigTable,
+    systems::generators::GenContext,
+    voxel::VoxelKind,
+};
+
+/// The default height of the sea. Air below it is filled with water.
+pub const DEFAULT_SEA_LEVEL: usize = 32;
+
+/// Columns at most this many blocks above sea level can become beaches.
+const BEACH_HEIGHT: usize = 2;
+/// How many columns away from the water beaches reach.
+const BEACH_WIDTH: usize = 3;
+
+/// Sea floors at most this many blocks below sea level are covered with sand,
+/// and deeper ones with gravel.
+const SHALLOW_DEPTH: usize = 4;
+
+/// Reads the optional `sea_level` parameter. Zero means there is no water.
+pub fn sea_level_param(params: &ConfigTable) -> Result<usize, String> {
+    match params.get("sea_level") {
+        Some(level) => level
+            .as_integer()
+            .and_then(|level| usize::try_from(level).ok())
+            .filter(|level| *level <= CHUNK_HEIGHT)
+            .ok_or_else(|| format!("'sea_level' must be between 0 and {}", CHUNK_HEIGHT)),
+        None => Ok(DEFAULT_SEA_LEVEL),
+    }
+}
+
+/// Fills the air above every column lower than the sea level with water, and
+/// covers the sea floor with sand or gravel. Columns on the shore that are
+/// close to sea level are turned into sand beaches.
+///
+/// `height` returns the height of the column at a world position. It is also
+/// used for columns in neighbouring chunks, to find water near the border.
+pub fn flood(
+    chunk: &mut Chunk,
+    ctx: &GenContext,
+    sea_level: usize,
+    height: impl Fn(i32, i32) -> usize,
+) {
+    if sea_level == 0 {
+        return;
+    }
+
+    let water = ctx.block("water");
+    let sand = ctx.block("sand");
+    let gravel = ctx.block("gravel");
+
+    // The heights of the chunk's columns and those around it, looked up as needed
+    let margin = BEACH_WIDTH as i32;
+    let size = CHUNK_WIDTH + BEACH_WIDTH * 2;
+    let (origin_x, _, origin_z) = chunk.world_position((0, 0, 0));
+
+    let mut heights = vec![None; size * size];
+    let mut height_at = |x: i32, z: i32| {
+        let index = (x + margin) as usize * size + (z + margin) as usize;
+
+        *heights[index].get_or_insert_with(|| height(origin_x + x, origin_z + z))
+    };
+
+    for x in 0..CHUNK_WIDTH {
+        for z in 0..CHUNK_WIDTH {
+            let column_height = height_at(x as i32, z as i32);
+
+            let surface = if column_height < sea_level {
+                for y in column_height..sea_level {
+                    if chunk
+                        .get((x, y, z))
+                        .is_some_and(|voxel| voxel.kind == VoxelKind::AIR)
+                    {
+                        chunk.set((x, y, z), water);
+                    }
+                }
+
+                if sea_level - column_height <= SHALLOW_DEPTH {
+                    sand
+                } else {
+                    gravel
+                }
+            } else if column_height <= sea_level + BEACH_HEIGHT {
+                let near_water = (-margin..=margin).any(|dx| {
+                    (-margin..=margin).any(|dz| {
+                        dx * dx + dz * dz <= margin * margin
+                            && height_at(x as i32 + dx, z as i32 + dz) < sea_level
+                    })
+                });
+
+                if !near_water {
+                    continue;
+                }
+
+                sand
+            } else {
+                continue;
+            };
+
+            // Columns where a cave has broken through the surface are left alone
+            if column_height > 0
+                && chunk
+                    .get((x, column_height - 1, z))
+                    .is_some_and(|voxel| voxel.kind != VoxelKind::AIR)
+            {
+                chunk.set((x, column_height - 1, z), surface);
+            }
+        }
+    }
+}
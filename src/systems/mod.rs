@@ -1,2 +1,2 @@
-pub mod chunk_builder;
 pub mod chunk_manager;
+pub mod generators;
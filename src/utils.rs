@@ -1,18 +1,44 @@
 #![allow(dead_code)]
 
-use crate::chunk::{CHUNK_HEIGHT, CHUNK_WIDTH};
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_HEIGHT};
 
-/// Returns the index of a block given its position in the chunk.
-/// For example, (0, 10, 0) -> 10. Used for indexing into the chunk's
-/// `cubes` array.
-pub fn get_chunk_index(chunk_coord: (usize, usize, usize)) -> usize {
+/// Returns the index of a block given its position in a chunk section.
+/// For example, (0, 1, 0) -> 16. Used for indexing into the section's
+/// `BlockCollection`.
+pub fn get_section_index(section_coord: (usize, usize, usize)) -> usize {
+    let (x, y, z) = section_coord;
+
+    x + CHUNK_WIDTH * (y + SECTION_HEIGHT * z)
+}
+
+/// Returns the position of a block in a chunk section given its index.
+/// This is the inverse of `get_section_index`, for example 16 -> (0, 1, 0).
+pub fn get_section_coordinate(index: usize) -> (usize, usize, usize) {
+    (
+        index % CHUNK_WIDTH,
+        (index / CHUNK_WIDTH) % SECTION_HEIGHT,
+        index / (CHUNK_WIDTH * SECTION_HEIGHT),
+    )
+}
+
+/// Returns the position of a block in the world, given the position of its
+/// chunk and its position within that chunk.
+/// For example, ((1, 0), (1, 0, 0)) -> (17, 0, 0).
+pub fn chunk_to_world_position(
+    chunk_pos: (i32, i32),
+    chunk_coord: (usize, usize, usize),
+) -> (i32, i32, i32) {
     let (x, y, z) = chunk_coord;
 
-    x + CHUNK_WIDTH * (y + CHUNK_HEIGHT * z)
+    (
+        x as i32 + chunk_pos.0 * CHUNK_WIDTH as i32,
+        y as i32,
+        z as i32 + chunk_pos.1 * CHUNK_WIDTH as i32,
+    )
 }
 
 /// Returns the coordinate of a chunk, given a block's position in the world.
-/// For example, (17, 0, 0) -> (1, 0).
+/// For example, (17, 0) -> (1, 0) and (-1, 0) -> (-1, 0).
 pub fn world_to_chunk_position(x: i32, z: i32) -> (i32, i32) {
     (
         x.div_euclid(CHUNK_WIDTH as i32),
@@ -20,9 +46,9 @@ pub fn world_to_chunk_position(x: i32, z: i32) -> (i32, i32) {
     )
 }
 
-/// Return the chunk position of a block given its position in the world.
-/// For example, (17, 0, 0) -> (1, 0, 0). Used for indexing into the
-/// `chunks` array.
+/// Returns the position of a block within its chunk, given its position in
+/// the world. For example, (17, 0, 0) -> (1, 0, 0) and (-1, 0, -17) ->
+/// (15, 0, 15). Used for indexing into the chunk.
 pub fn world_to_chunk_coordinate(x: i32, y: i32, z: i32) -> (usize, usize, usize) {
     (
         x.rem_euclid(CHUNK_WIDTH as i32) as usize,
@@ -71,3 +97,41 @@ macro_rules! get_gl_error {
         })
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_index_round_trips() {
+        assert_eq!(get_section_index((0, 1, 0)), 16);
+        assert_eq!(get_section_coordinate(16), (0, 1, 0));
+
+        for index in 0..CHUNK_WIDTH * SECTION_HEIGHT * CHUNK_WIDTH {
+            assert_eq!(get_section_index(get_section_coordinate(index)), index);
+        }
+    }
+
+    #[test]
+    fn world_positions_split_into_chunks() {
+        assert_eq!(world_to_chunk_position(17, 0), (1, 0));
+        assert_eq!(world_to_chunk_position(-1, 0), (-1, 0));
+        assert_eq!(world_to_chunk_position(-16, -17), (-1, -2));
+
+        assert_eq!(world_to_chunk_coordinate(17, 0, 0), (1, 0, 0));
+        assert_eq!(world_to_chunk_coordinate(-1, 0, -17), (15, 0, 15));
+        assert_eq!(world_to_chunk_coordinate(-16, 5, 0), (0, 5, 0));
+    }
+
+    #[test]
+    fn chunk_positions_join_into_world_positions() {
+        assert_eq!(chunk_to_world_position((1, 0), (1, 0, 0)), (17, 0, 0));
+
+        for (x, z) in [(17, 0), (-1, -17), (-16, 31), (0, -32)] {
+            let chunk = world_to_chunk_position(x, z);
+            let local = world_to_chunk_coordinate(x, 3, z);
+
+            assert_eq!(chunk_to_world_position(chunk, local), (x, 3, z));
+        }
+    }
+}
@@ -1,19 +1,38 @@
+use crate::block_state::BlockState;
+
 /// Represents a voxel in the world.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Voxel {
-    /// The position of the voxel.
-    pub position: (i32, i32, i32),
-
     /// The kind of voxel.
     pub kind: VoxelKind,
+
+    /// The properties of the voxel, such as its orientation.
+    pub state: BlockState,
+}
+
+impl Voxel {
+    /// Creates a voxel with the default state.
+    pub fn new(kind: VoxelKind) -> Self {
+        Self::with_state(kind, BlockState::default())
+    }
+
+    /// Creates a voxel with the given state.
+    pub fn with_state(kind: VoxelKind, state: BlockState) -> Self {
+        Self { kind, state }
+    }
 }
 
-/// The types of voxels.
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum VoxelKind {
-    /// Air (empty space).
-    Air,
+/// The kind of a voxel, as a compact ID into the `BlockRegistry`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoxelKind(pub u16);
+
+impl VoxelKind {
+    /// Air (empty space). Always has the ID 0.
+    pub const AIR: VoxelKind = VoxelKind(0);
+}
 
-    /// Grass
-    Grass,
+impl From<VoxelKind> for Voxel {
+    fn from(kind: VoxelKind) -> Self {
+        Self::new(kind)
+    }
 }
@@ -0,0 +1,144 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_HEIGHT},
+    utils::{world_to_chunk_coordinate, world_to_chunk_position},
+    voxel::Voxel,
+};
+
+/// All of the chunks that have been built, addressed by their position.
+/// Allows blocks to be read and written by their world coordinates.
+#[derive(Debug, Default)]
+pub struct World {
+    /// The chunks, keyed by their chunk position.
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl World {
+    /// Creates a new, empty world.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk to the world, returning the chunk it replaced (if any).
+    pub fn insert(&mut self, chunk: Chunk) -> Option<Chunk> {
+        self.chunks.insert(chunk.position, chunk)
+    }
+
+    /// Removes a chunk from the world.
+    pub fn remove(&mut self, chunk_pos: (i32, i32)) -> Option<Chunk> {
+        self.chunks.remove(&chunk_pos)
+    }
+
+    /// Returns true if the chunk at the given position has been built.
+    pub fn contains_chunk(&self, chunk_pos: (i32, i32)) -> bool {
+        self.chunks.contains_key(&chunk_pos)
+    }
+
+    /// Gets the chunk at the given chunk position.
+    pub fn get_chunk(&self, chunk_pos: (i32, i32)) -> Option<&Chunk> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    /// Gets a mutable reference to the chunk at the given chunk position.
+    pub fn get_chunk_mut(&mut self, chunk_pos: (i32, i32)) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&chunk_pos)
+    }
+
+    /// Iterates over every chunk in the world.
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    /// Mutably iterates over every chunk in the world.
+    pub fn chunks_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.values_mut()
+    }
+
+    /// Returns the number of chunks in the world.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if the world has no chunks.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Gets the block at the given world position.
+    /// Returns `None` if the chunk hasn't been built, or the position is above
+    /// or below the world.
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<Voxel> {
+        if !(0..CHUNK_HEIGHT as i32).contains(&y) {
+            return None;
+        }
+
+        self.get_chunk(world_to_chunk_position(x, z))?
+            .get(world_to_chunk_coordinate(x, y, z))
+    }
+
+    /// Sets the block at the given world position, and marks the chunk (and any
+    /// neighbouring chunk bordering the block) as needing a remesh. The chunk is
+    /// also marked as modified, so that it gets saved.
+    /// Returns false if the chunk hasn't been built, or the position is above
+    /// or below the world.
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, voxel: impl Into<Voxel>) -> bool {
+        if !(0..CHUNK_HEIGHT as i32).contains(&y) {
+            return false;
+        }
+
+        let voxel = voxel.into();
+        let (cx, cz) = world_to_chunk_position(x, z);
+        let (lx, ly, lz) = world_to_chunk_coordinate(x, y, z);
+
+        let chunk = match self.get_chunk_mut((cx, cz)) {
+            Some(chunk) => chunk,
+            None => return false,
+        };
+
+        if chunk.get((lx, ly, lz)) == Some(voxel) {
+            return true;
+        }
+
+        chunk.set((lx, ly, lz), voxel);
+        chunk.modified = true;
+
+        // Faces of the neighbouring chunks may have been hidden or exposed, and
+        // blocks on a corner also shade the faces of the diagonal chunk
+        let section = ly / SECTION_HEIGHT;
+        let last = CHUNK_WIDTH - 1;
+
+        let side = |local: usize| match local {
+            0 => -1,
+            local if local == last => 1,
+            _ => 0,
+        };
+        let (dx, dz) = (side(lx), side(lz));
+
+        if dx != 0 {
+            self.mark_dirty((cx + dx, cz), section);
+        }
+        if dz != 0 {
+            self.mark_dirty((cx, cz + dz), section);
+        }
+        if dx != 0 && dz != 0 {
+            self.mark_dirty((cx + dx, cz + dz), section);
+        }
+
+        true
+    }
+
+    /// Marks a section of a chunk as needing a remesh, if the chunk exists.
+    pub fn mark_dirty(&mut self, chunk_pos: (i32, i32), section: usize) {
+        if let Some(chunk) = self.get_chunk_mut(chunk_pos) {
+            chunk.sections[section].dirty = true;
+        }
+    }
+
+    /// Returns the approximate number of bytes used by all chunks.
+    pub fn memory_usage(&self) -> usize {
+        self.chunks().map(Chunk::memory_usage).sum()
+    }
+}